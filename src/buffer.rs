@@ -1,8 +1,13 @@
-use std::cell::{Cell, RefCell};
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell, RefMut};
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
-use std::io;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::num::NonZeroUsize;
 use std::ops::{Index, IndexMut};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
 
 use crate::disk::{DiskManager, PageId, PAGE_SIZE};
 
@@ -13,10 +18,90 @@ pub enum Error {
     Io(#[from] io::Error),
     #[error("no free buffer available in buffer pool")]
     NoFreeBuffer,
+    #[error("cyclic dependency between pages")]
+    CyclicDependency,
+    #[error("page {0:?} has write barriers that need a sync")]
+    PendingBarrier(PageId),
+    #[error("buffer {0:?} is pinned")]
+    Pinned(BufferId),
+    #[error("page {0:?} has uncommitted changes")]
+    Uncommitted(PageId),
+    #[error("buffer {0:?} is out of range")]
+    BufferOutOfRange(BufferId),
+    #[error("invalid buffer pool state: {0}")]
+    InvalidState(#[from] bincode::Error),
+    #[error("buffer pool state is for {expected} frames, but the pool has {actual}")]
+    PoolSizeMismatch { expected: usize, actual: usize },
 }
 
 #[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
-pub struct BufferId(usize);
+pub struct BufferId(pub usize);
+
+// Priority hint for fetching a page.
+// It biases the initial used_count of the frame, so high priority pages survive more clock sweeps.
+// The clock still decays them over time, so they are not pinned forever.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Priority {
+    // e.g. a scan's current page, which can go as soon as it's unpinned
+    #[default]
+    Normal,
+    // e.g. index root pages, which should essentially never be evicted
+    High,
+}
+
+impl Priority {
+    fn used_count(self) -> u64 {
+        match self {
+            Priority::Normal => 1,
+            Priority::High => 5,
+        }
+    }
+}
+
+// Accumulated counters of the buffer pool manager
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct BufferPoolStats {
+    pub hits: u64,
+    pub misses: u64,
+    // number of resident pages that were replaced to make room for another page
+    pub evictions: u64,
+    // number of fetches of a page whose previous Rc was still outstanding (only counted in debug mode)
+    pub double_fetches: u64,
+}
+
+impl BufferPoolStats {
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            return 1.0;
+        }
+        self.hits as f64 / total as f64
+    }
+}
+
+// Advisory result of BufferPoolManager::size_recommendation
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SizeAdvice {
+    Grow { target: usize },
+    Shrink { target: usize },
+    Keep,
+}
+
+// Serialized form of the cache metadata, see BufferPoolManager::serialize_state
+#[derive(Debug, Serialize, Deserialize)]
+struct PoolState {
+    pool_size: usize,
+    next_victim_id: usize,
+    frames: Vec<FrameState>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FrameState {
+    buffer_id: usize,
+    page_id: u64,
+    used_count: u64,
+    wired: bool,
+}
 
 pub type Page = [u8; PAGE_SIZE];
 
@@ -24,7 +109,14 @@ pub type Page = [u8; PAGE_SIZE];
 pub struct Buffer {
     pub page_id: PageId,
     pub page: RefCell<Page>,
-    pub is_dirty: Cell<bool>,
+    // NOTE: only changed through set_dirty, which keeps dirty_buffers in sync
+    is_dirty: Cell<bool>,
+    // number of mark_dirty calls since the page was loaded
+    writes: Cell<u64>,
+    // when the page turned dirty, for flush_aged
+    dirtied_at: Cell<Option<Instant>>,
+    // number of dirty buffers in the pool, shared by all of its buffers
+    dirty_buffers: Rc<Cell<usize>>,
 }
 
 impl Default for Buffer {
@@ -33,32 +125,95 @@ impl Default for Buffer {
             page_id: Default::default(),
             page: RefCell::new([0u8; PAGE_SIZE]),
             is_dirty: Cell::new(false),
+            writes: Cell::new(0),
+            dirtied_at: Cell::new(None),
+            dirty_buffers: Default::default(),
         }
     }
 }
 
+impl Buffer {
+    pub fn is_dirty(&self) -> bool {
+        self.is_dirty.get()
+    }
+
+    // Mark the page as modified and count the write for rw_histogram
+    pub fn mark_dirty(&self) {
+        self.set_dirty(true);
+        self.writes.set(self.writes.get() + 1);
+    }
+
+    // Every dirty/clean transition goes through here
+    fn set_dirty(&self, is_dirty: bool) {
+        match (self.is_dirty.get(), is_dirty) {
+            (false, true) => {
+                self.dirtied_at.set(Some(Instant::now()));
+                self.dirty_buffers.set(self.dirty_buffers.get() + 1);
+            }
+            (true, false) => {
+                self.dirtied_at.set(None);
+                self.dirty_buffers.set(self.dirty_buffers.get() - 1);
+            }
+            _ => {}
+        }
+        self.is_dirty.set(is_dirty);
+    }
+
+    // Empty the buffer for another page
+    fn reset(&mut self) {
+        self.set_dirty(false);
+        self.page_id = PageId::default();
+        self.page.get_mut().fill(0);
+        self.writes.set(0);
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct Frame {
     used_count: u64,
     buffer: Rc<Buffer>,
+    // wired frames stay resident until unwired, even when nobody holds the buffer
+    wired: bool,
+    // number of fetches since the page was loaded
+    reads: u64,
 }
 
 pub struct BufferPool {
     buffers: Vec<Frame>,
     next_victim_id: BufferId,
+    // frames evict may look at per call, None means until every frame is known to be pinned
+    max_sweep_steps: Option<NonZeroUsize>,
+    // number of dirty buffers, kept up to date by the buffers themselves
+    dirty_buffers: Rc<Cell<usize>>,
 }
 
 impl BufferPool {
     pub fn new(pool_size: usize) -> Self {
         let mut buffers = vec![];
-        buffers.resize_with(pool_size, Default::default);
+        let dirty_buffers = Rc::new(Cell::new(0));
+        buffers.resize_with(pool_size, || Frame {
+            buffer: Rc::new(Buffer {
+                dirty_buffers: Rc::clone(&dirty_buffers),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
         let next_victim_id = BufferId::default();
         Self {
             buffers,
             next_victim_id,
+            max_sweep_steps: None,
+            dirty_buffers,
         }
     }
 
+    // Bound the work of a single eviction to cap the tail latency of fetch_page.
+    // NOTE: evict may then give up (NoFreeBuffer) while an unpinned frame still exists.
+    //       It takes at least one step, otherwise no frame could ever be evicted
+    pub fn set_max_sweep_steps(&mut self, max_sweep_steps: NonZeroUsize) {
+        self.max_sweep_steps = Some(max_sweep_steps);
+    }
+
     fn size(&self) -> usize {
         self.buffers.len()
     }
@@ -68,15 +223,21 @@ impl BufferPool {
         let pool_size = self.size();
         // consecutive_pinned is used for judging whether all frame is used.
         let mut consecutive_pinned = 0;
+        let mut steps = 0;
         let victim_id = loop {
+            if self.max_sweep_steps.map(NonZeroUsize::get) == Some(steps) {
+                return None;
+            }
+            steps += 1;
             let next_victim_id = self.next_victim_id;
             let frame = &mut self[next_victim_id];
-            if frame.used_count == 0 {
+            // NOTE: wired frames are skipped regardless of used_count, they count as pinned
+            if !frame.wired && frame.used_count == 0 {
                 break self.next_victim_id;
             }
             // NOTE: Rc::get_mut returns a mutable reference to the contained value
             // So this expression means "if the frame being not borrowed"
-            if Rc::get_mut(&mut frame.buffer).is_some() {
+            if !frame.wired && Rc::get_mut(&mut frame.buffer).is_some() {
                 frame.used_count -= 1;
                 consecutive_pinned = 0;
             } else {
@@ -115,70 +276,398 @@ pub struct BufferPoolManager {
     buffer_pool: BufferPool,
     // The page table keeps track of pages that are currently in memory
     page_table: HashMap<PageId, BufferId>,
+    // Write barriers: a page must not be flushed before the pages it depends on
+    dependencies: HashMap<PageId, HashSet<PageId>>,
+    // pages written by the running BufferTransaction, which must not reach disk before it ends
+    uncommitted: HashSet<PageId>,
+    stats: BufferPoolStats,
+    // count double fetches in stats, to catch pin-accounting bugs
+    debug_double_fetch: bool,
 }
 
 impl BufferPoolManager {
     pub fn new(disk_manager: DiskManager, buffer_pool: BufferPool) -> Self {
         let page_table = HashMap::new();
+        let dependencies = HashMap::new();
         Self {
             disk_manager,
             buffer_pool,
-            page_table
+            page_table,
+            dependencies,
+            uncommitted: HashSet::new(),
+            stats: BufferPoolStats::default(),
+            debug_double_fetch: false,
         }
     }
 
+    // Debug aid: count in stats().double_fetches when a page is fetched while a previous Rc of it
+    // is still outstanding.
+    // NOTE: such an outstanding Rc keeps the frame pinned, so it can never be evicted
+    pub fn set_debug_double_fetch(&mut self, enabled: bool) {
+        self.debug_double_fetch = enabled;
+    }
+
+    // Load the pages and seed their used_count, as if they had been accessed that often recently
+    // (e.g. restoring a warm set after restart, instead of loading it cold at used_count = 1).
+    // The loads don't count as hits, misses or reads, which describe the workload (evictions still count).
+    // NOTE: warming more pages than the pool holds evicts the earlier ones again
+    pub fn warm_up_with_priority(
+        &mut self,
+        page_ids: &[PageId],
+        used_count: u64,
+    ) -> Result<(), Error> {
+        for &page_id in page_ids {
+            if !self.page_table.contains_key(&page_id) {
+                self.load_page(page_id, Priority::Normal)?;
+            }
+        }
+        // NOTE: seeded after loading them all, so that loading one doesn't decay another
+        for page_id in page_ids {
+            if let Some(&buffer_id) = self.page_table.get(page_id) {
+                self.buffer_pool[buffer_id].used_count = used_count;
+            }
+        }
+        Ok(())
+    }
+
+    // Keep the page resident until unwire_page, loading it if needed.
+    // This is stronger than pinning because it persists across Rc drops (e.g. header, catalog root).
+    pub fn wire_page(&mut self, page_id: PageId) -> Result<(), Error> {
+        self.fetch_page(page_id)?;
+        let buffer_id = self.page_table[&page_id];
+        self.buffer_pool[buffer_id].wired = true;
+        Ok(())
+    }
+
+    pub fn unwire_page(&mut self, page_id: PageId) {
+        if let Some(&buffer_id) = self.page_table.get(&page_id) {
+            self.buffer_pool[buffer_id].wired = false;
+        }
+    }
+
+    // Begin a transaction that records the pages it dirties, so that they can be rolled back
+    pub fn begin(&mut self) -> BufferTransaction<'_> {
+        BufferTransaction {
+            bufmgr: self,
+            undo: HashMap::new(),
+        }
+    }
+
+    // Whether any resident page is dirty, e.g. to skip a checkpoint. O(1)
+    pub fn has_dirty(&self) -> bool {
+        self.buffer_pool.dirty_buffers.get() > 0
+    }
+
+    pub fn stats(&self) -> BufferPoolStats {
+        self.stats
+    }
+
+    // (reads, writes) of every resident page since it was loaded, to tell read-mostly pages from write-heavy ones.
+    // Reads are fetches, writes are Buffer::mark_dirty calls.
+    pub fn rw_histogram(&self) -> HashMap<PageId, (u64, u64)> {
+        self.page_table
+            .iter()
+            .map(|(&page_id, &buffer_id)| {
+                let frame = &self.buffer_pool[buffer_id];
+                (page_id, (frame.reads, frame.buffer.writes.get()))
+            })
+            .collect()
+    }
+
+    // Suggest growing, shrinking or keeping the pool size based on the accumulated stats.
+    // It is purely advisory, nothing is changed.
+    pub fn size_recommendation(&self) -> SizeAdvice {
+        // below this hit rate an evicting pool is considered to be thrashing
+        const MIN_HIT_RATE: f64 = 0.9;
+        let pool_size = self.buffer_pool.size();
+        if self.stats.hits + self.stats.misses == 0 {
+            return SizeAdvice::Keep;
+        }
+        if self.stats.evictions > 0 {
+            if self.stats.hit_rate() < MIN_HIT_RATE {
+                return SizeAdvice::Grow { target: pool_size * 2 };
+            }
+            return SizeAdvice::Keep;
+        }
+        // Nothing has been evicted, so the resident pages are the whole working set
+        let working_set = self.page_table.len();
+        if working_set < pool_size / 2 {
+            return SizeAdvice::Shrink { target: working_set.max(1) };
+        }
+        SizeAdvice::Keep
+    }
+
+    // Register that page_id must not reach disk before dependency_page_id
+    // (e.g. a heap tuple must be durable before the index entry pointing to it).
+    // Both flushes and evictions honor the ordering. The registrations are cleared once the page is written.
+    // Fails with Error::CyclicDependency if dependency_page_id already depends on page_id
+    // (or is page_id itself), because no order could satisfy both. Nothing is registered then.
+    pub fn depends_on(&mut self, page_id: PageId, dependency_page_id: PageId) -> Result<(), Error> {
+        if self.reaches(dependency_page_id, page_id) {
+            return Err(Error::CyclicDependency);
+        }
+        self.dependencies
+            .entry(page_id)
+            .or_default()
+            .insert(dependency_page_id);
+        Ok(())
+    }
+
+    // Whether target is from or one of the (transitive) dependencies of from
+    fn reaches(&self, from: PageId, target: PageId) -> bool {
+        let mut stack = vec![from];
+        let mut visited = HashSet::new();
+        while let Some(page_id) = stack.pop() {
+            if page_id == target {
+                return true;
+            }
+            if !visited.insert(page_id) {
+                continue;
+            }
+            if let Some(dependencies) = self.dependencies.get(&page_id) {
+                stack.extend(dependencies.iter().copied());
+            }
+        }
+        false
+    }
+
     pub fn fetch_page(&mut self, page_id: PageId) -> Result<Rc<Buffer>, Error> {
+        self.fetch_page_with_priority(page_id, Priority::Normal)
+    }
+
+    pub fn fetch_page_with_priority(
+        &mut self,
+        page_id: PageId,
+        priority: Priority,
+    ) -> Result<Rc<Buffer>, Error> {
         // If the page is in the buffer pool
         if let Some(&buffer_id) = self.page_table.get(&page_id) {
+            self.stats.hits += 1;
             let frame = &mut self.buffer_pool[buffer_id];
-            frame.used_count += 1;
+            // NOTE: the frame holds one Rc itself
+            if self.debug_double_fetch && Rc::strong_count(&frame.buffer) > 1 {
+                self.stats.double_fetches += 1;
+            }
+            // NOTE: a high priority hit makes the frame at least as sticky as a high priority load
+            frame.used_count = (frame.used_count + 1).max(priority.used_count());
+            frame.reads += 1;
             // NOTE: Rc::clone is not deep copy.
             //       It just increment the reference count and pass the reference.
             return Ok(Rc::clone(&frame.buffer));
         }
         // If the page is not in the buffer pool, read the page from disk and save the data on buffer pool.
         // To save the page on buffer pool, make decision of which frame is available
+        self.stats.misses += 1;
+        let buffer_id = self.load_page(page_id, priority)?;
+        let frame = &mut self.buffer_pool[buffer_id];
+        frame.reads = 1;
+        Ok(Rc::clone(&frame.buffer))
+    }
+
+    // Read a page that is not resident into a free frame, without counting it as a fetch
+    fn load_page(&mut self, page_id: PageId, priority: Priority) -> Result<BufferId, Error> {
         let buffer_id = self.buffer_pool.evict().ok_or(Error::NoFreeBuffer)?;
+        self.write_dependencies(buffer_id)?;
         let available_frame = &mut self.buffer_pool[buffer_id];
         let evict_page_id = available_frame.buffer.page_id;
+        if evict_page_id.valid().is_some() {
+            self.stats.evictions += 1;
+        }
         {
             // Before clearing buffer: if the buffer's data was changed (dirty flag is true), update page data in disk
             // NOTE: Option<T> can be explicitly handled via match or implicitly with unwrap.
             //       unwrap either return the inner element or panic
             // NOTE: Rc::get_mut returns a mutable reference to the contained value
             let available_buffer = Rc::get_mut(&mut available_frame.buffer).unwrap();
-            if available_buffer.is_dirty.get() {
+            if available_buffer.is_dirty() {
                 // NOTE: ? operator early returns an Err(e)
                 self.disk_manager.write_page_data(evict_page_id, available_buffer.page.get_mut())?;
             }
             // Reading the page data from disk
+            available_buffer.reset();
             available_buffer.page_id = page_id;
-            available_buffer.is_dirty.set(false);
             self.disk_manager.read_page_data(page_id, available_buffer.page.get_mut())?;
-            available_frame.used_count = 1;
+            available_frame.used_count = priority.used_count();
+            available_frame.reads = 0;
         }
 
         // Updating the page table
-        let page = Rc::clone(&available_frame.buffer);
         self.page_table.remove(&evict_page_id);
         self.page_table.insert(page_id, buffer_id);
-        Ok(page)
+        Ok(buffer_id)
+    }
+
+    // Run f with the page and release the pin when it returns (or panics),
+    // so that no Rc can leak out of the closure
+    pub fn with_page<R>(
+        &mut self,
+        page_id: PageId,
+        f: impl FnOnce(&Buffer) -> R,
+    ) -> Result<R, Error> {
+        let buffer = self.fetch_page(page_id)?;
+        Ok(f(&buffer))
+    }
+
+    // Frame holding the page, None if the page is not resident
+    pub fn frame_of(&self, page_id: PageId) -> Option<BufferId> {
+        self.page_table.get(&page_id).copied()
+    }
+
+    // Number of outstanding Rcs of a resident page, 0 if the page is not resident
+    pub fn pin_count(&self, page_id: PageId) -> usize {
+        match self.page_table.get(&page_id) {
+            // NOTE: the frame holds one Rc itself
+            Some(&buffer_id) => Rc::strong_count(&self.buffer_pool[buffer_id].buffer) - 1,
+            None => 0,
+        }
+    }
+
+    // Write which page each frame holds and the eviction metadata (clock hand, used counts, wiring),
+    // so that a standby can restore_state and take over with a warm cache.
+    // NOTE: page contents are not included, restore_state reads them from disk.
+    //       Flush first, or the dirty pages are restored with their old contents
+    pub fn serialize_state(&self, out: &mut impl Write) -> Result<(), Error> {
+        let mut frames: Vec<_> = self
+            .page_table
+            .iter()
+            .map(|(&page_id, &buffer_id)| {
+                let frame = &self.buffer_pool[buffer_id];
+                FrameState {
+                    buffer_id: buffer_id.0,
+                    page_id: page_id.to_u64(),
+                    used_count: frame.used_count,
+                    wired: frame.wired,
+                }
+            })
+            .collect();
+        frames.sort_by_key(|frame| frame.buffer_id);
+        let state = PoolState {
+            pool_size: self.buffer_pool.size(),
+            next_victim_id: self.buffer_pool.next_victim_id.0,
+            frames,
+        };
+        bincode::serialize_into(out, &state)?;
+        Ok(())
+    }
+
+    // Load the pages listed by serialize_state into the same frames and restore the eviction metadata.
+    // NOTE: the pool must have the same size as the serialized one.
+    //       A bad state or a pinned or wired frame fails before anything is loaded.
+    //       An I/O error fails in the middle: the frames loaded before it stay restored,
+    //       the others keep what they held, and the clock hand is not restored.
+    pub fn restore_state(&mut self, input: &mut impl Read) -> Result<(), Error> {
+        let state: PoolState = bincode::deserialize_from(input)?;
+        if state.pool_size != self.buffer_pool.size() {
+            return Err(Error::PoolSizeMismatch {
+                expected: state.pool_size,
+                actual: self.buffer_pool.size(),
+            });
+        }
+        // validate every index upfront, so that a bad state doesn't leave the pool half restored
+        let buffer_ids = state.frames.iter().map(|frame_state| frame_state.buffer_id);
+        for buffer_id in buffer_ids.chain([state.next_victim_id]) {
+            if buffer_id >= state.pool_size {
+                return Err(Error::BufferOutOfRange(BufferId(buffer_id)));
+            }
+        }
+        // the same for the frames load_into_frame would refuse
+        for frame_state in &state.frames {
+            let buffer_id = BufferId(frame_state.buffer_id);
+            match self.page_table.get(&PageId(frame_state.page_id)) {
+                Some(&old_buffer_id) if old_buffer_id == buffer_id => continue,
+                Some(&old_buffer_id) => self.check_unpinned(old_buffer_id)?,
+                None => {}
+            }
+            self.check_unpinned(buffer_id)?;
+        }
+        for frame_state in state.frames {
+            let buffer_id = BufferId(frame_state.buffer_id);
+            self.load_into_frame(PageId(frame_state.page_id), buffer_id)?;
+            let frame = &mut self.buffer_pool[buffer_id];
+            frame.used_count = frame_state.used_count;
+            frame.wired = frame_state.wired;
+        }
+        self.buffer_pool.next_victim_id = BufferId(state.next_victim_id);
+        Ok(())
+    }
+
+    // Load the page into the given frame, bypassing the clock (for tests and deterministic placement).
+    // Whatever the frame holds is written back if dirty and evicted.
+    // If the page is resident in another frame, it is moved.
+    // NOTE: fails with Error::Pinned if either frame is pinned or wired
+    pub fn load_into_frame(&mut self, page_id: PageId, buffer_id: BufferId) -> Result<(), Error> {
+        if buffer_id.0 >= self.buffer_pool.size() {
+            return Err(Error::BufferOutOfRange(buffer_id));
+        }
+        if self.page_table.get(&page_id) == Some(&buffer_id) {
+            return Ok(());
+        }
+        if let Some(&old_buffer_id) = self.page_table.get(&page_id) {
+            self.check_unpinned(old_buffer_id)?;
+        }
+        self.check_unpinned(buffer_id)?;
+        if let Some(&old_buffer_id) = self.page_table.get(&page_id) {
+            // write the page back so that the target frame reads the latest contents
+            self.evict_frame(old_buffer_id)?;
+        }
+        self.evict_frame(buffer_id)?;
+        let frame = &mut self.buffer_pool[buffer_id];
+        let buffer = Rc::get_mut(&mut frame.buffer).unwrap();
+        buffer.page_id = page_id;
+        self.disk_manager.read_page_data(page_id, buffer.page.get_mut())?;
+        frame.used_count = 1;
+        frame.reads = 1;
+        self.page_table.insert(page_id, buffer_id);
+        Ok(())
+    }
+
+    fn check_unpinned(&mut self, buffer_id: BufferId) -> Result<(), Error> {
+        let frame = &mut self.buffer_pool[buffer_id];
+        if frame.wired || Rc::get_mut(&mut frame.buffer).is_none() {
+            return Err(Error::Pinned(buffer_id));
+        }
+        Ok(())
+    }
+
+    // Write back and empty an unpinned frame
+    fn evict_frame(&mut self, buffer_id: BufferId) -> Result<(), Error> {
+        self.write_dependencies(buffer_id)?;
+        let frame = &mut self.buffer_pool[buffer_id];
+        let buffer = Rc::get_mut(&mut frame.buffer).unwrap();
+        let evict_page_id = buffer.page_id;
+        if evict_page_id.valid().is_none() {
+            return Ok(());
+        }
+        if buffer.is_dirty() {
+            self.disk_manager.write_page_data(evict_page_id, buffer.page.get_mut())?;
+        }
+        buffer.reset();
+        frame.used_count = 0;
+        frame.reads = 0;
+        self.page_table.remove(&evict_page_id);
+        self.stats.evictions += 1;
+        Ok(())
     }
 
     pub fn create_page(&mut self) -> Result<Rc<Buffer>, Error> {
         let buffer_id = self.buffer_pool.evict().ok_or(Error::NoFreeBuffer)?;
+        self.write_dependencies(buffer_id)?;
         let available_frame = &mut self.buffer_pool[buffer_id];
         let evict_page_id = available_frame.buffer.page_id;
+        if evict_page_id.valid().is_some() {
+            self.stats.evictions += 1;
+        }
         let page_id = {
             let available_buffer = Rc::get_mut(&mut available_frame.buffer).unwrap();
-            if available_buffer.is_dirty.get() {
+            if available_buffer.is_dirty() {
                 self.disk_manager.write_page_data(evict_page_id, available_buffer.page.get_mut())?;
             }
             let page_id = self.disk_manager.allocate_page();
-            *available_buffer = Buffer::default();
+            available_buffer.reset();
             available_buffer.page_id = page_id;
-            available_buffer.is_dirty.set(true);
+            available_buffer.set_dirty(true);
             available_frame.used_count = 1;
+            available_frame.reads = 0;
             page_id
         };
         let page = Rc::clone(&available_frame.buffer);
@@ -189,27 +678,306 @@ impl BufferPoolManager {
     }
 
     pub fn flush(&mut self) -> Result<(), Error> {
-        for (&page_id, &buffer_id) in self.page_table.iter() {
-            let frame = &self.buffer_pool[buffer_id];
+        self.flush_written()?;
+        Ok(())
+    }
+
+    // Flush and return the ids of the dirty pages that were written (e.g. to ship them to a replica)
+    pub fn flush_written(&mut self) -> Result<Vec<PageId>, Error> {
+        let order = self.flush_order()?;
+        let written = self.write_dirty_pages(order)?;
+        self.sync()?;
+        // every dependency has been written in order
+        self.dependencies.clear();
+        Ok(written)
+    }
+
+    // Write the dirty resident pages without making them durable, so that several flushes can share one sync.
+    // Returns the ids of the written pages, clean pages are skipped.
+    // Fails with Error::PendingBarrier if a dirty page has write barriers (depends_on),
+    // because keeping their order takes a sync. Nothing is written then, use flush instead.
+    pub fn flush_no_sync(&mut self) -> Result<Vec<PageId>, Error> {
+        for page_id in self.dependencies.keys() {
+            if let Some(&buffer_id) = self.page_table.get(page_id) {
+                if self.buffer_pool[buffer_id].buffer.is_dirty() {
+                    return Err(Error::PendingBarrier(*page_id));
+                }
+            }
+        }
+        let order = self.flush_order()?;
+        let written = self.write_dirty_pages(order)?;
+        // the remaining barriers are of pages that are already on disk
+        self.dependencies.clear();
+        Ok(written)
+    }
+
+    // Flush the pages that have been dirty for longer than max_age, to bound the recovery time.
+    // Meant to be called periodically. Returns the number of written pages.
+    // NOTE: the pages they depend on are written too, even if they are younger
+    pub fn flush_aged(&mut self, max_age: Duration) -> Result<usize, Error> {
+        let now = Instant::now();
+        let aged: Vec<PageId> = self
+            .page_table
+            .iter()
+            .filter(|(_, &buffer_id)| {
+                let buffer = &self.buffer_pool[buffer_id].buffer;
+                buffer
+                    .dirtied_at
+                    .get()
+                    .is_some_and(|dirtied_at| now.duration_since(dirtied_at) > max_age)
+            })
+            .map(|(&page_id, _)| page_id)
+            .collect();
+        let mut visited = HashMap::new();
+        let mut order = vec![];
+        for page_id in aged {
+            self.visit_dependencies(page_id, &mut visited, &mut order)?;
+        }
+        let written = self.write_dirty_pages(order.iter().copied())?;
+        // NOTE: an idle call must not cost a sync, it is meant to be called often
+        if !written.is_empty() {
+            self.sync()?;
+        }
+        // the barriers of the written pages are satisfied
+        for page_id in &order {
+            self.dependencies.remove(page_id);
+        }
+        Ok(written.len())
+    }
+
+    // Before the page in the frame is written back by an eviction,
+    // write the dirty pages it depends on (transitively) and make them durable
+    fn write_dependencies(&mut self, buffer_id: BufferId) -> Result<(), Error> {
+        let buffer = &self.buffer_pool[buffer_id].buffer;
+        let page_id = buffer.page_id;
+        if !buffer.is_dirty() || !self.dependencies.contains_key(&page_id) {
+            return Ok(());
+        }
+        let mut visited = HashMap::new();
+        let mut order = vec![];
+        self.visit_dependencies(page_id, &mut visited, &mut order)?;
+        // NOTE: the last one is the evicted page itself, the eviction writes it
+        order.pop();
+        self.write_dirty_pages(order.iter().copied())?;
+        self.disk_manager.sync()?;
+        for page_id in order.iter().chain([&page_id]) {
+            self.dependencies.remove(page_id);
+        }
+        Ok(())
+    }
+
+    // Write the dirty pages in the given (dependency) order
+    fn write_dirty_pages(
+        &mut self,
+        order: impl IntoIterator<Item = PageId>,
+    ) -> Result<Vec<PageId>, Error> {
+        let mut written = vec![];
+        for page_id in order {
+            let frame = &self.buffer_pool[self.page_table[&page_id]];
+            if !frame.buffer.is_dirty() {
+                continue;
+            }
+            // NOTE: only reachable through write barriers, the transaction pins its pages
+            if self.uncommitted.contains(&page_id) {
+                return Err(Error::Uncommitted(page_id));
+            }
+            // NOTE: the OS may reorder unsynced writes,
+            //       so make the dependencies durable before writing a dependent page
+            if self.dependencies.contains_key(&page_id) {
+                self.disk_manager.sync()?;
+            }
             let mut page = frame.buffer.page.borrow_mut();
             self.disk_manager.write_page_data(page_id, page.as_mut())?;
-            frame.buffer.is_dirty.set(false);
+            frame.buffer.set_dirty(false);
+            written.push(page_id);
         }
+        Ok(written)
+    }
+
+    pub fn sync(&mut self) -> Result<(), Error> {
         self.disk_manager.sync()?;
         Ok(())
     }
+
+    // Order the resident pages so that dependencies come before their dependents (topological sort)
+    fn flush_order(&self) -> Result<Vec<PageId>, Error> {
+        // pages being visited are false, visited pages are true
+        let mut visited = HashMap::new();
+        let mut order = Vec::with_capacity(self.page_table.len());
+        for &page_id in self.page_table.keys() {
+            self.visit_dependencies(page_id, &mut visited, &mut order)?;
+        }
+        Ok(order)
+    }
+
+    fn visit_dependencies(
+        &self,
+        page_id: PageId,
+        visited: &mut HashMap<PageId, bool>,
+        order: &mut Vec<PageId>,
+    ) -> Result<(), Error> {
+        match visited.get(&page_id) {
+            Some(true) => return Ok(()),
+            // reached a page that is still being visited
+            Some(false) => return Err(Error::CyclicDependency),
+            None => {}
+        }
+        visited.insert(page_id, false);
+        if let Some(dependencies) = self.dependencies.get(&page_id) {
+            for &dependency_page_id in dependencies {
+                self.visit_dependencies(dependency_page_id, visited, order)?;
+            }
+        }
+        visited.insert(page_id, true);
+        // NOTE: pages that are not resident are already on disk
+        if self.page_table.contains_key(&page_id) {
+            order.push(page_id);
+        }
+        Ok(())
+    }
+}
+
+// Minimal in-memory undo without WAL.
+// The pre-transaction contents of a page are saved on its first write in the transaction.
+// NOTE: The written pages stay pinned until the transaction ends, so they are never written back
+//       to disk by an eviction before commit (no-steal). The transaction borrows the manager,
+//       so flush can't write them back either.
+//       Evicting a page that depends on a written page (depends_on) would have to write it first,
+//       so that eviction fails with Error::Uncommitted instead.
+pub struct BufferTransaction<'a> {
+    bufmgr: &'a mut BufferPoolManager,
+    undo: HashMap<PageId, UndoPage>,
+}
+
+struct UndoPage {
+    buffer: Rc<Buffer>,
+    page: Box<Page>,
+    is_dirty: bool,
+}
+
+impl<'a> BufferTransaction<'a> {
+    pub fn fetch_page(&mut self, page_id: PageId) -> Result<Rc<Buffer>, Error> {
+        self.bufmgr.fetch_page(page_id)
+    }
+
+    pub fn create_page(&mut self) -> Result<Rc<Buffer>, Error> {
+        self.bufmgr.create_page()
+    }
+
+    // Borrow the page for writing and mark it dirty.
+    // NOTE: writes that don't go through here are not rolled back
+    pub fn write<'b>(&mut self, buffer: &'b Rc<Buffer>) -> RefMut<'b, Page> {
+        self.bufmgr.uncommitted.insert(buffer.page_id);
+        self.undo.entry(buffer.page_id).or_insert_with(|| UndoPage {
+            buffer: Rc::clone(buffer),
+            page: Box::new(*buffer.page.borrow()),
+            is_dirty: buffer.is_dirty(),
+        });
+        buffer.mark_dirty();
+        buffer.page.borrow_mut()
+    }
+
+    // Keep the changes and discard the undo data
+    pub fn commit(self) {}
+
+    // Restore the pre-transaction contents and dirty flags of every written page
+    pub fn rollback(mut self) {
+        for undo in mem::take(&mut self.undo).into_values() {
+            *undo.buffer.page.borrow_mut() = *undo.page;
+            undo.buffer.set_dirty(undo.is_dirty);
+        }
+    }
+}
+
+impl Drop for BufferTransaction<'_> {
+    fn drop(&mut self) {
+        // NOTE: the transaction borrows the manager mutably, so it is the only one running
+        self.bufmgr.uncommitted.clear();
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::disk::Storage;
+    use std::io::{Read, Seek, SeekFrom, Write};
+    use std::panic;
+    use std::sync::{Arc, Mutex};
     use tempfile::NamedTempFile;
 
+    #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+    enum Op {
+        // write to the page at the stream position
+        Write(PageId),
+        Sync,
+    }
+
+    // in-memory storage recording the I/O operations issued to it
+    struct RecordingStorage {
+        inner: io::Cursor<Vec<u8>>,
+        log: Arc<Mutex<Vec<Op>>>,
+    }
+
+    impl RecordingStorage {
+        fn new() -> (Self, Arc<Mutex<Vec<Op>>>) {
+            let log = Arc::new(Mutex::new(vec![]));
+            let storage = Self {
+                inner: io::Cursor::new(vec![]),
+                log: Arc::clone(&log),
+            };
+            (storage, log)
+        }
+    }
+
+    impl Read for RecordingStorage {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.inner.read(buf)
+        }
+    }
+
+    impl Write for RecordingStorage {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let page_id = PageId(self.inner.position() / PAGE_SIZE as u64);
+            self.log.lock().unwrap().push(Op::Write(page_id));
+            self.inner.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.inner.flush()
+        }
+    }
+
+    impl Seek for RecordingStorage {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    impl Storage for RecordingStorage {
+        fn sync_all(&mut self) -> io::Result<()> {
+            self.log.lock().unwrap().push(Op::Sync);
+            Ok(())
+        }
+    }
+
+    // disk manager over a temp file that already holds num_pages zeroed pages
+    fn disk_with_pages(num_pages: usize) -> (DiskManager, Vec<PageId>) {
+        let mut disk_manager = DiskManager::new_nondurable(tempfile::tempfile().unwrap()).unwrap();
+        let page_ids = (0..num_pages)
+            .map(|_| {
+                let page_id = disk_manager.allocate_page();
+                disk_manager.write_page_data(page_id, &[0u8; PAGE_SIZE]).unwrap();
+                page_id
+            })
+            .collect();
+        (disk_manager, page_ids)
+    }
+
     #[test]
     fn test() {
         // create temp file
         let (data_file, data_file_path) = NamedTempFile::new().unwrap().into_parts();
-        let mut disk_manager = DiskManager::new(data_file).unwrap();
+        let mut disk_manager = DiskManager::new_nondurable(data_file).unwrap();
         // NOTE: allocate heap memory.
         //       Vec::with_capasity creates a vector with the given capasity but with zero length.
         //       (capasity: 4096, length: 0)
@@ -233,7 +1001,7 @@ mod tests {
         // remove disk manager
         drop(disk_manager);
         // create new disk manager
-        let mut disk_manager2 = DiskManager::open(&data_file_path).unwrap();
+        let mut disk_manager2 = DiskManager::open_nondurable(&data_file_path).unwrap();
         // NOTE: (capasity:4096, length:4096)
         let mut buffer = vec![0; PAGE_SIZE];
         disk_manager2.read_page_data(hello_page_id, &mut buffer).unwrap();
@@ -241,4 +1009,575 @@ mod tests {
         disk_manager2.read_page_data(world_page_id, &mut buffer).unwrap();
         assert_eq!(world, buffer);
     }
+
+    #[test]
+    fn test_depends_on() {
+        let (storage, log) = RecordingStorage::new();
+        let disk_manager = DiskManager::new(storage).unwrap();
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(4));
+        let page_a = bufmgr.create_page().unwrap();
+        let page_b = bufmgr.create_page().unwrap();
+        let page_c = bufmgr.create_page().unwrap();
+        bufmgr.depends_on(page_b.page_id, page_a.page_id).unwrap();
+        bufmgr.depends_on(page_c.page_id, page_b.page_id).unwrap();
+        bufmgr.flush().unwrap();
+        assert!(!page_a.is_dirty());
+        assert!(!page_b.is_dirty());
+        // each page reaches disk only after the page it depends on has been synced
+        let expected = vec![
+            Op::Write(page_a.page_id),
+            Op::Sync,
+            Op::Write(page_b.page_id),
+            Op::Sync,
+            Op::Write(page_c.page_id),
+            Op::Sync,
+        ];
+        assert_eq!(expected, *log.lock().unwrap());
+
+        // closing the cycle C -> B -> A -> C is refused and registers nothing
+        bufmgr.depends_on(page_b.page_id, page_a.page_id).unwrap();
+        bufmgr.depends_on(page_c.page_id, page_b.page_id).unwrap();
+        assert!(matches!(
+            bufmgr.depends_on(page_a.page_id, page_c.page_id),
+            Err(Error::CyclicDependency)
+        ));
+        assert!(matches!(
+            bufmgr.depends_on(page_a.page_id, page_a.page_id),
+            Err(Error::CyclicDependency)
+        ));
+        assert!(!bufmgr.dependencies.contains_key(&page_a.page_id));
+        // so flushing still works
+        page_a.mark_dirty();
+        page_c.mark_dirty();
+        log.lock().unwrap().clear();
+        bufmgr.flush().unwrap();
+        let expected = vec![
+            Op::Write(page_a.page_id),
+            Op::Sync,
+            Op::Write(page_c.page_id),
+            Op::Sync,
+        ];
+        assert_eq!(expected, *log.lock().unwrap());
+    }
+
+    #[test]
+    fn test_depends_on_eviction() {
+        let (storage, log) = RecordingStorage::new();
+        let disk_manager = DiskManager::new(storage).unwrap();
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(2));
+        let page_a = bufmgr.create_page().unwrap();
+        let page_b_id = bufmgr.create_page().unwrap().page_id;
+        bufmgr.depends_on(page_b_id, page_a.page_id).unwrap();
+        // A is pinned, so making room evicts B, which must not reach disk before A
+        let page_c = bufmgr.create_page().unwrap();
+        let expected = vec![Op::Write(page_a.page_id), Op::Sync, Op::Write(page_b_id)];
+        assert_eq!(expected, *log.lock().unwrap());
+        assert!(!page_a.is_dirty());
+        assert!(bufmgr.dependencies.is_empty());
+
+        // the same when load_into_frame evicts B
+        let page_c_id = page_c.page_id;
+        drop(page_c);
+        bufmgr.fetch_page(page_b_id).unwrap().mark_dirty();
+        page_a.mark_dirty();
+        bufmgr.depends_on(page_b_id, page_a.page_id).unwrap();
+        log.lock().unwrap().clear();
+        let buffer_id = bufmgr.frame_of(page_b_id).unwrap();
+        bufmgr.load_into_frame(page_c_id, buffer_id).unwrap();
+        assert_eq!(expected, *log.lock().unwrap());
+    }
+
+    #[test]
+    fn test_size_recommendation() {
+        let (disk_manager, page_ids) = disk_with_pages(8);
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(16));
+        assert_eq!(SizeAdvice::Keep, bufmgr.size_recommendation());
+        // a small working set fits easily
+        for _ in 0..4 {
+            bufmgr.fetch_page(page_ids[0]).unwrap();
+            bufmgr.fetch_page(page_ids[1]).unwrap();
+        }
+        assert_eq!(SizeAdvice::Shrink { target: 2 }, bufmgr.size_recommendation());
+
+        // cycling over more pages than the pool holds thrashes it
+        let (disk_manager, page_ids) = disk_with_pages(8);
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(4));
+        for _ in 0..4 {
+            for &page_id in &page_ids {
+                bufmgr.fetch_page(page_id).unwrap();
+            }
+        }
+        let stats = bufmgr.stats();
+        assert!(stats.evictions > 0);
+        assert!(stats.hit_rate() < 0.5);
+        assert_eq!(SizeAdvice::Grow { target: 8 }, bufmgr.size_recommendation());
+    }
+
+    #[test]
+    fn test_transaction() {
+        let (disk_manager, page_ids) = disk_with_pages(2);
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(4));
+        let clean = bufmgr.fetch_page(page_ids[0]).unwrap();
+        let dirty = bufmgr.fetch_page(page_ids[1]).unwrap();
+        dirty.page.borrow_mut()[..5].copy_from_slice(b"dirty");
+        dirty.mark_dirty();
+
+        let mut tx = bufmgr.begin();
+        tx.write(&clean)[..5].copy_from_slice(b"hello");
+        tx.write(&dirty)[..5].copy_from_slice(b"world");
+        // the second write to the same page keeps the first pre-image
+        tx.write(&clean)[5..10].copy_from_slice(b"again");
+        assert!(clean.is_dirty());
+        tx.rollback();
+        assert_eq!([0u8; PAGE_SIZE], *clean.page.borrow());
+        assert!(!clean.is_dirty());
+        assert_eq!(b"dirty", &dirty.page.borrow()[..5]);
+        assert!(dirty.is_dirty());
+
+        let mut tx = bufmgr.begin();
+        let page = tx.fetch_page(page_ids[0]).unwrap();
+        tx.write(&page)[..5].copy_from_slice(b"hello");
+        tx.commit();
+        assert_eq!(b"hello", &clean.page.borrow()[..5]);
+        assert!(clean.is_dirty());
+    }
+
+    #[test]
+    fn test_transaction_depends_on() {
+        let (disk_manager, page_ids) = disk_with_pages(3);
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(2));
+        let page_a = bufmgr.fetch_page(page_ids[0]).unwrap();
+        bufmgr.fetch_page(page_ids[1]).unwrap().mark_dirty();
+        bufmgr.depends_on(page_ids[1], page_ids[0]).unwrap();
+
+        let mut tx = bufmgr.begin();
+        tx.write(&page_a)[0] = 42;
+        // evicting B would write A, which the transaction may still roll back
+        assert!(matches!(
+            tx.fetch_page(page_ids[2]),
+            Err(Error::Uncommitted(page_id)) if page_id == page_ids[0]
+        ));
+        tx.rollback();
+        assert_eq!(0, page_a.page.borrow()[0]);
+        drop(page_a);
+        bufmgr.flush().unwrap();
+        let mut buf = vec![0; PAGE_SIZE];
+        bufmgr.disk_manager.read_page_data(page_ids[0], &mut buf).unwrap();
+        assert_eq!(0, buf[0]);
+
+        // once committed, the eviction writes A before B as usual
+        let page_a = bufmgr.fetch_page(page_ids[0]).unwrap();
+        bufmgr.fetch_page(page_ids[1]).unwrap().mark_dirty();
+        bufmgr.depends_on(page_ids[1], page_ids[0]).unwrap();
+        let mut tx = bufmgr.begin();
+        tx.write(&page_a)[0] = 42;
+        tx.commit();
+        bufmgr.fetch_page(page_ids[2]).unwrap();
+        bufmgr.disk_manager.read_page_data(page_ids[0], &mut buf).unwrap();
+        assert_eq!(42, buf[0]);
+    }
+
+    #[test]
+    fn test_flush_no_sync() {
+        let (storage, log) = RecordingStorage::new();
+        let disk_manager = DiskManager::new(storage).unwrap();
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(4));
+        bufmgr.create_page().unwrap();
+        bufmgr.create_page().unwrap();
+        bufmgr.flush_no_sync().unwrap();
+        assert_eq!(2, log.lock().unwrap().len());
+        assert!(!log.lock().unwrap().contains(&Op::Sync));
+        log.lock().unwrap().clear();
+        bufmgr.sync().unwrap();
+        assert_eq!(vec![Op::Sync], *log.lock().unwrap());
+
+        // pages with write barriers can't be written without syncing
+        let page_a = bufmgr.create_page().unwrap();
+        let page_b = bufmgr.create_page().unwrap();
+        bufmgr.depends_on(page_b.page_id, page_a.page_id).unwrap();
+        log.lock().unwrap().clear();
+        assert!(matches!(
+            bufmgr.flush_no_sync(),
+            Err(Error::PendingBarrier(page_id)) if page_id == page_b.page_id
+        ));
+        assert!(log.lock().unwrap().is_empty());
+        assert!(page_a.is_dirty());
+        // once they are written, only the page without barriers is dirty again
+        bufmgr.flush().unwrap();
+        page_a.mark_dirty();
+        log.lock().unwrap().clear();
+        bufmgr.flush_no_sync().unwrap();
+        assert_eq!(vec![Op::Write(page_a.page_id)], *log.lock().unwrap());
+    }
+
+    #[test]
+    fn test_flush_written() {
+        let (storage, log) = RecordingStorage::new();
+        let disk_manager = DiskManager::new(storage).unwrap();
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(4));
+        let pages: Vec<_> = (0..3).map(|_| bufmgr.create_page().unwrap()).collect();
+        bufmgr.flush().unwrap();
+        pages[0].mark_dirty();
+        pages[2].mark_dirty();
+        log.lock().unwrap().clear();
+        let mut written = bufmgr.flush_written().unwrap();
+        written.sort_by_key(|page_id| page_id.to_u64());
+        assert_eq!(vec![pages[0].page_id, pages[2].page_id], written);
+        let ops = log.lock().unwrap().clone();
+        assert!(ops[..2].contains(&Op::Write(pages[0].page_id)));
+        assert!(ops[..2].contains(&Op::Write(pages[2].page_id)));
+        assert_eq!([Op::Sync], ops[2..]);
+        // nothing is dirty anymore
+        assert_eq!(Vec::<PageId>::new(), bufmgr.flush_written().unwrap());
+    }
+
+    #[test]
+    fn test_flush_aged() {
+        let (storage, log) = RecordingStorage::new();
+        let disk_manager = DiskManager::new(storage).unwrap();
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(4));
+        let pages: Vec<_> = (0..3).map(|_| bufmgr.create_page().unwrap()).collect();
+        bufmgr.flush().unwrap();
+        pages[0].mark_dirty();
+        pages[1].mark_dirty();
+        std::thread::sleep(Duration::from_millis(20));
+        pages[2].mark_dirty();
+        // page 1 must reach disk before page 0, even though it's as old
+        bufmgr.depends_on(pages[0].page_id, pages[1].page_id).unwrap();
+        log.lock().unwrap().clear();
+        assert_eq!(2, bufmgr.flush_aged(Duration::from_millis(10)).unwrap());
+        assert!(!pages[0].is_dirty());
+        assert!(!pages[1].is_dirty());
+        assert!(pages[2].is_dirty());
+        let expected = vec![
+            Op::Write(pages[1].page_id),
+            Op::Sync,
+            Op::Write(pages[0].page_id),
+            Op::Sync,
+        ];
+        assert_eq!(expected, *log.lock().unwrap());
+        assert!(bufmgr.dependencies.is_empty());
+        // nothing old enough, so nothing is written or synced
+        log.lock().unwrap().clear();
+        assert_eq!(0, bufmgr.flush_aged(Duration::from_secs(60)).unwrap());
+        assert!(log.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_has_dirty() {
+        let (disk_manager, page_ids) = disk_with_pages(2);
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(1));
+        assert!(!bufmgr.has_dirty());
+        let page = bufmgr.fetch_page(page_ids[0]).unwrap();
+        assert!(!bufmgr.has_dirty());
+        page.mark_dirty();
+        page.mark_dirty();
+        assert!(bufmgr.has_dirty());
+        drop(page);
+        bufmgr.flush().unwrap();
+        assert!(!bufmgr.has_dirty());
+
+        // written back by an eviction
+        bufmgr.fetch_page(page_ids[0]).unwrap().mark_dirty();
+        bufmgr.fetch_page(page_ids[1]).unwrap();
+        assert!(!bufmgr.has_dirty());
+
+        // rolled back
+        let page = bufmgr.fetch_page(page_ids[1]).unwrap();
+        let mut tx = bufmgr.begin();
+        tx.write(&page)[0] = 1;
+        tx.rollback();
+        assert!(!bufmgr.has_dirty());
+        drop(page);
+        bufmgr.create_page().unwrap();
+        assert!(bufmgr.has_dirty());
+    }
+
+    #[test]
+    fn test_nondurable_flush() {
+        let (storage, log) = RecordingStorage::new();
+        let disk_manager = DiskManager::new_nondurable(storage).unwrap();
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(4));
+        let page_id = bufmgr.create_page().unwrap().page_id;
+        bufmgr.flush().unwrap();
+        assert_eq!(vec![Op::Write(page_id)], *log.lock().unwrap());
+    }
+
+    #[test]
+    fn test_debug_double_fetch() {
+        let (disk_manager, page_ids) = disk_with_pages(1);
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(2));
+        bufmgr.set_debug_double_fetch(true);
+        let first = bufmgr.fetch_page(page_ids[0]).unwrap();
+        assert_eq!(0, bufmgr.stats().double_fetches);
+        let second = bufmgr.fetch_page(page_ids[0]).unwrap();
+        assert_eq!(1, bufmgr.stats().double_fetches);
+        drop(first);
+        drop(second);
+        bufmgr.fetch_page(page_ids[0]).unwrap();
+        assert_eq!(1, bufmgr.stats().double_fetches);
+    }
+
+    #[test]
+    fn test_wire_page() {
+        let (disk_manager, page_ids) = disk_with_pages(8);
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(2));
+        bufmgr.wire_page(page_ids[0]).unwrap();
+        for _ in 0..4 {
+            for &page_id in &page_ids[1..] {
+                bufmgr.fetch_page(page_id).unwrap();
+            }
+        }
+        assert!(bufmgr.page_table.contains_key(&page_ids[0]));
+        // the only other frame is pinned
+        let pinned = bufmgr.fetch_page(page_ids[1]).unwrap();
+        assert!(matches!(bufmgr.fetch_page(page_ids[2]), Err(Error::NoFreeBuffer)));
+        drop(pinned);
+
+        bufmgr.unwire_page(page_ids[0]);
+        for &page_id in &page_ids[1..] {
+            bufmgr.fetch_page(page_id).unwrap();
+        }
+        assert!(!bufmgr.page_table.contains_key(&page_ids[0]));
+    }
+
+    #[test]
+    fn test_rw_histogram() {
+        let (disk_manager, page_ids) = disk_with_pages(2);
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(1));
+        bufmgr.fetch_page(page_ids[0]).unwrap();
+        bufmgr.fetch_page(page_ids[0]).unwrap();
+        let page = bufmgr.fetch_page(page_ids[0]).unwrap();
+        page.mark_dirty();
+        drop(page);
+        assert_eq!(HashMap::from([(page_ids[0], (3, 1))]), bufmgr.rw_histogram());
+        // the counters start over when the page is loaded again
+        bufmgr.fetch_page(page_ids[1]).unwrap().mark_dirty();
+        assert_eq!(HashMap::from([(page_ids[1], (1, 1))]), bufmgr.rw_histogram());
+        bufmgr.fetch_page(page_ids[0]).unwrap();
+        assert_eq!(HashMap::from([(page_ids[0], (1, 0))]), bufmgr.rw_histogram());
+    }
+
+    #[test]
+    fn test_load_into_frame() {
+        let (disk_manager, page_ids) = disk_with_pages(8);
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(4));
+        let page = bufmgr.fetch_page(page_ids[7]).unwrap();
+        page.page.borrow_mut()[0] = 7;
+        page.mark_dirty();
+        assert!(matches!(
+            bufmgr.load_into_frame(page_ids[7], BufferId(2)),
+            Err(Error::Pinned(BufferId(0)))
+        ));
+        drop(page);
+
+        bufmgr.load_into_frame(page_ids[7], BufferId(2)).unwrap();
+        assert_eq!(Some(&BufferId(2)), bufmgr.page_table.get(&page_ids[7]));
+        assert_eq!(1, bufmgr.page_table.len());
+        // the dirty contents moved along with the page
+        assert_eq!(7, bufmgr.fetch_page(page_ids[7]).unwrap().page.borrow()[0]);
+
+        // whatever the frame held is replaced
+        bufmgr.load_into_frame(page_ids[1], BufferId(2)).unwrap();
+        assert_eq!(Some(&BufferId(2)), bufmgr.page_table.get(&page_ids[1]));
+        assert!(!bufmgr.page_table.contains_key(&page_ids[7]));
+        let pinned = bufmgr.fetch_page(page_ids[1]).unwrap();
+        assert!(matches!(
+            bufmgr.load_into_frame(page_ids[3], BufferId(2)),
+            Err(Error::Pinned(BufferId(2)))
+        ));
+        drop(pinned);
+        assert_eq!(7, bufmgr.fetch_page(page_ids[7]).unwrap().page.borrow()[0]);
+        assert!(matches!(
+            bufmgr.load_into_frame(page_ids[3], BufferId(4)),
+            Err(Error::BufferOutOfRange(BufferId(4)))
+        ));
+    }
+
+    #[test]
+    fn test_with_page() {
+        let (disk_manager, page_ids) = disk_with_pages(1);
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(1));
+        let page = bufmgr.fetch_page(page_ids[0]).unwrap();
+        page.page.borrow_mut()[..5].copy_from_slice(b"hello");
+        assert_eq!(1, bufmgr.pin_count(page_ids[0]));
+        drop(page);
+
+        let bytes = bufmgr
+            .with_page(page_ids[0], |buffer| buffer.page.borrow()[..5].to_vec())
+            .unwrap();
+        assert_eq!(b"hello".to_vec(), bytes);
+        assert_eq!(0, bufmgr.pin_count(page_ids[0]));
+
+        // the pin is released even if the closure panics
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            bufmgr.with_page(page_ids[0], |_| panic!("boom")).unwrap();
+        }));
+        assert!(result.is_err());
+        assert_eq!(0, bufmgr.pin_count(page_ids[0]));
+        assert_eq!(0, bufmgr.pin_count(PageId(42)));
+    }
+
+    #[test]
+    fn test_max_sweep_steps() {
+        let (disk_manager, page_ids) = disk_with_pages(5);
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(4));
+        // frames 0 to 2 are pinned, frame 3 holds an unpinned page
+        let _pinned: Vec<_> = page_ids[..3]
+            .iter()
+            .map(|&page_id| bufmgr.fetch_page(page_id).unwrap())
+            .collect();
+        bufmgr.fetch_page(page_ids[3]).unwrap();
+        bufmgr.buffer_pool.set_max_sweep_steps(NonZeroUsize::new(1).unwrap());
+        // the single step only decrements the used_count of frame 3
+        assert!(matches!(bufmgr.fetch_page(page_ids[4]), Err(Error::NoFreeBuffer)));
+        // the hand moved on to frame 0, so 3 steps only cover the pinned frames
+        bufmgr.buffer_pool.set_max_sweep_steps(NonZeroUsize::new(3).unwrap());
+        assert!(matches!(bufmgr.fetch_page(page_ids[4]), Err(Error::NoFreeBuffer)));
+        bufmgr.buffer_pool.set_max_sweep_steps(NonZeroUsize::new(4).unwrap());
+        bufmgr.fetch_page(page_ids[4]).unwrap();
+        assert!(!bufmgr.page_table.contains_key(&page_ids[3]));
+    }
+
+    #[test]
+    fn test_serialize_state() {
+        let data_file = NamedTempFile::new().unwrap();
+        let disk_manager = DiskManager::open_nondurable(data_file.path()).unwrap();
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(4));
+        let page_ids: Vec<_> = (0..5)
+            .map(|i| {
+                let page = bufmgr.create_page().unwrap();
+                page.page.borrow_mut()[0] = i;
+                page.page_id
+            })
+            .collect();
+        bufmgr.fetch_page(page_ids[2]).unwrap();
+        bufmgr.fetch_page_with_priority(page_ids[4], Priority::High).unwrap();
+        bufmgr.wire_page(page_ids[3]).unwrap();
+        bufmgr.flush().unwrap();
+        let mut state = vec![];
+        bufmgr.serialize_state(&mut state).unwrap();
+
+        let disk_manager = DiskManager::open_nondurable(data_file.path()).unwrap();
+        let mut standby = BufferPoolManager::new(disk_manager, BufferPool::new(4));
+        standby.restore_state(&mut state.as_slice()).unwrap();
+        assert_eq!(bufmgr.page_table, standby.page_table);
+        assert_eq!(bufmgr.buffer_pool.next_victim_id, standby.buffer_pool.next_victim_id);
+        for (page_id, &buffer_id) in &bufmgr.page_table {
+            let (frame, restored) = (
+                &bufmgr.buffer_pool[buffer_id],
+                &standby.buffer_pool[buffer_id],
+            );
+            assert_eq!(frame.used_count, restored.used_count);
+            assert_eq!(frame.wired, restored.wired);
+            assert_eq!(*frame.buffer.page.borrow(), *restored.buffer.page.borrow());
+            assert_eq!(*page_id, restored.buffer.page_id);
+        }
+
+        let disk_manager = DiskManager::open_nondurable(data_file.path()).unwrap();
+        let mut smaller = BufferPoolManager::new(disk_manager, BufferPool::new(2));
+        assert!(matches!(
+            smaller.restore_state(&mut state.as_slice()),
+            Err(Error::PoolSizeMismatch { expected: 4, actual: 2 })
+        ));
+
+        // indexes past the pool are rejected before anything is loaded
+        let frame_state = |buffer_id| FrameState {
+            buffer_id,
+            page_id: page_ids[0].to_u64(),
+            used_count: 1,
+            wired: false,
+        };
+        let bad_states = [
+            PoolState {
+                pool_size: 4,
+                next_victim_id: 0,
+                frames: vec![frame_state(0), frame_state(4)],
+            },
+            PoolState {
+                pool_size: 4,
+                next_victim_id: 4,
+                frames: vec![frame_state(0)],
+            },
+        ];
+        for bad_state in bad_states {
+            let disk_manager = DiskManager::open_nondurable(data_file.path()).unwrap();
+            let mut standby = BufferPoolManager::new(disk_manager, BufferPool::new(4));
+            let state = bincode::serialize(&bad_state).unwrap();
+            assert!(matches!(
+                standby.restore_state(&mut state.as_slice()),
+                Err(Error::BufferOutOfRange(BufferId(4)))
+            ));
+            assert!(standby.page_table.is_empty());
+        }
+
+        // so are pinned frames
+        let disk_manager = DiskManager::open_nondurable(data_file.path()).unwrap();
+        let mut standby = BufferPoolManager::new(disk_manager, BufferPool::new(4));
+        standby.load_into_frame(page_ids[0], BufferId(3)).unwrap();
+        let pinned = standby.fetch_page(page_ids[0]).unwrap();
+        assert!(matches!(
+            standby.restore_state(&mut state.as_slice()),
+            Err(Error::Pinned(BufferId(3)))
+        ));
+        assert_eq!(HashMap::from([(page_ids[0], BufferId(3))]), standby.page_table);
+        drop(pinned);
+        standby.restore_state(&mut state.as_slice()).unwrap();
+        assert_eq!(bufmgr.page_table, standby.page_table);
+    }
+
+    #[test]
+    fn test_warm_up_with_priority() {
+        let (disk_manager, page_ids) = disk_with_pages(6);
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(4));
+        bufmgr.warm_up_with_priority(&page_ids[..2], 5).unwrap();
+        for page_id in &page_ids[..2] {
+            assert_eq!(5, bufmgr.buffer_pool[bufmgr.page_table[page_id]].used_count);
+        }
+        // warming up is not part of the workload
+        assert_eq!(BufferPoolStats::default(), bufmgr.stats());
+        assert_eq!(
+            HashMap::from([(page_ids[0], (0, 0)), (page_ids[1], (0, 0))]),
+            bufmgr.rw_histogram()
+        );
+        // the warmed pages outlive pages loaded cold
+        for &page_id in &page_ids[2..] {
+            bufmgr.fetch_page(page_id).unwrap();
+        }
+        bufmgr.fetch_page(page_ids[2]).unwrap();
+        assert!(bufmgr.page_table.contains_key(&page_ids[0]));
+        assert!(bufmgr.page_table.contains_key(&page_ids[1]));
+    }
+
+    #[test]
+    fn test_frame_of() {
+        let (disk_manager, page_ids) = disk_with_pages(2);
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(1));
+        bufmgr.fetch_page(page_ids[1]).unwrap();
+        let buffer_id = bufmgr.frame_of(page_ids[1]).unwrap();
+        assert_eq!(page_ids[1], bufmgr.buffer_pool[buffer_id].buffer.page_id);
+        assert_eq!(None, bufmgr.frame_of(page_ids[0]));
+    }
+
+    #[test]
+    fn test_fetch_page_with_priority() {
+        let (disk_manager, page_ids) = disk_with_pages(16);
+        let mut bufmgr = BufferPoolManager::new(disk_manager, BufferPool::new(3));
+        // load both pages at the same time, the buffers are unpinned right away
+        bufmgr.fetch_page(page_ids[0]).unwrap();
+        bufmgr.fetch_page_with_priority(page_ids[1], Priority::High).unwrap();
+        // keep loading other pages so that the clock sweeps over both frames
+        bufmgr.fetch_page(page_ids[2]).unwrap();
+        bufmgr.fetch_page(page_ids[3]).unwrap();
+        assert!(!bufmgr.page_table.contains_key(&page_ids[0]));
+        assert!(bufmgr.page_table.contains_key(&page_ids[1]));
+        bufmgr.fetch_page(page_ids[4]).unwrap();
+        bufmgr.fetch_page(page_ids[5]).unwrap();
+        assert!(bufmgr.page_table.contains_key(&page_ids[1]));
+        // the clock still decays the high priority page eventually
+        for &page_id in &page_ids[6..] {
+            bufmgr.fetch_page(page_id).unwrap();
+        }
+        assert!(!bufmgr.page_table.contains_key(&page_ids[1]));
+    }
 }
\ No newline at end of file
@@ -1,10 +1,19 @@
 use std::fs::{File, OpenOptions};
 use std::io::{self, prelude::*, SeekFrom};
 use std::path::Path;
+use std::sync::Once;
 use zerocopy::{AsBytes, FromBytes};
 
 pub const PAGE_SIZE: usize = 4096;
 
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("heap file shrank from {expected} to {actual} bytes")]
+    FileShrunk { expected: u64, actual: u64 },
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, FromBytes, AsBytes)]
 #[repr(C)]
 pub struct PageId(pub u64);
@@ -30,6 +39,81 @@ impl Default for PageId {
     }
 }
 
+// Storage is the stream the heap file lives on.
+// Anything that can read, write and seek can hold the pages (a file, memory, an encrypted volume wrapper,...).
+pub trait Storage: Read + Write + Seek {
+    // make the written data durable
+    // NOTE: streams without a durable medium have nothing to do
+    fn sync_all(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Storage for File {
+    fn sync_all(&mut self) -> io::Result<()> {
+        File::sync_all(self)
+    }
+}
+
+impl<T: AsRef<[u8]>> Storage for io::Cursor<T> where io::Cursor<T>: Write {}
+
+// Storage over a stream that has no durable medium of its own, see DiskManager::from_stream
+struct Stream<S>(S);
+
+impl<S: Read> Read for Stream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl<S: Write> Write for Stream<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl<S: Seek> Seek for Stream<S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
+impl<S: Read + Write + Seek> Storage for Stream<S> {}
+
+// Whether DiskManager::sync makes the written pages durable
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum Durability {
+    #[default]
+    Durable,
+    // sync only flushes to the OS and skips fsync, so data may be lost on crash.
+    // Meant for tests and CI workloads that create and drop databases repeatedly.
+    Nondurable,
+}
+
+// What DiskManager::sync does when the storage doesn't support sync_all
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum SyncMode {
+    // the error is returned
+    #[default]
+    Strict,
+    // the sync is downgraded to a plain flush
+    Lenient,
+}
+
+// Settings for DiskManager::new_with_options and open_with_options
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct DiskManagerOptions {
+    pub durability: Durability,
+    pub sync_mode: SyncMode,
+    // allocate_page never returns ids below this, even on an empty file (e.g. to partition ids across shards).
+    // NOTE: the file is left sparse, so the skipped pages can't be read until they are written
+    pub next_page_id_floor: u64,
+}
+
 // DiskManager stores the databases as file on disk. (proprietary binary format)
 // Organizing the files as a collection of pages.
 // - Page is fixed-size block of data (tuples, meta-data, indexes, log records,...)
@@ -37,33 +121,103 @@ impl Default for PageId {
 // TODO: Need to have Slot Array which contains tuple's starting position offset in case of deleting data.
 
 pub struct DiskManager {
-    // File descripter for heap file.
+    // Stream of the heap file (usually a file descriptor).
     // Heap file is an unordered collection of pages where tuples that are stored in random order.
     // Need meta-data to keep track of what pages exist and which ones have free space.
-    heap_file: File,
+    heap_file: Box<dyn Storage + Send>,
     // assigned page id
     next_page_id: u64,
+    durability: Durability,
+    sync_mode: SyncMode,
+    // bytes the heap file must have, i.e. the end of the farthest page written
+    // NOTE: allocated pages that were never written don't count, they may not be in the file yet
+    file_size: u64,
 }
 
 impl DiskManager {
-    pub fn new(heap_file: File) -> io::Result<Self> {
+    pub fn new(mut heap_file: impl Storage + Send + 'static) -> io::Result<Self> {
         // get file size
-        let heap_file_size = heap_file.metadata()?.len();
+        let heap_file_size = heap_file.seek(SeekFrom::End(0))?;
         let next_page_id = heap_file_size / PAGE_SIZE as u64;
         Ok(Self {
-            heap_file,
+            heap_file: Box::new(heap_file),
             next_page_id,
+            durability: Durability::Durable,
+            sync_mode: SyncMode::Strict,
+            file_size: heap_file_size,
         })
     }
 
+    // Layer the heap file over any stream (e.g. an encrypted volume wrapper), without implementing Storage.
+    // NOTE: sync only flushes the stream then, implement Storage to make it durable
+    pub fn from_stream(stream: impl Read + Write + Seek + Send + 'static) -> io::Result<Self> {
+        Self::new(Stream(stream))
+    }
+
+    pub fn new_nondurable(heap_file: impl Storage + Send + 'static) -> io::Result<Self> {
+        let options = DiskManagerOptions {
+            durability: Durability::Nondurable,
+            ..Default::default()
+        };
+        Self::new_with_options(heap_file, options)
+    }
+
+    pub fn new_with_options(
+        heap_file: impl Storage + Send + 'static,
+        options: DiskManagerOptions,
+    ) -> io::Result<Self> {
+        let mut disk_manager = Self::new(heap_file)?;
+        if options.durability == Durability::Nondurable {
+            // NOTE: once per process, tests create many nondurable managers.
+            //       Callers can tell each one apart through durability()
+            static NONDURABLE_WARNING: Once = Once::new();
+            NONDURABLE_WARNING.call_once(|| {
+                eprintln!("warning: disk manager is nondurable, data may be lost on crash");
+            });
+        }
+        disk_manager.durability = options.durability;
+        disk_manager.sync_mode = options.sync_mode;
+        disk_manager.next_page_id = disk_manager.next_page_id.max(options.next_page_id_floor);
+        Ok(disk_manager)
+    }
+
     // open by specifying the file path
     pub fn open(heap_file_path: impl AsRef<Path>) -> io::Result<Self> {
-        let heap_file = OpenOptions::new()
+        Self::new(Self::open_file(heap_file_path)?)
+    }
+
+    pub fn open_nondurable(heap_file_path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new_nondurable(Self::open_file(heap_file_path)?)
+    }
+
+    pub fn open_with_options(
+        heap_file_path: impl AsRef<Path>,
+        options: DiskManagerOptions,
+    ) -> io::Result<Self> {
+        Self::new_with_options(Self::open_file(heap_file_path)?, options)
+    }
+
+    fn open_file(heap_file_path: impl AsRef<Path>) -> io::Result<File> {
+        OpenOptions::new()
             .read(true)
             .write(true)
             .create(true)
-            .open(heap_file_path)?;
-        Self::new(heap_file)
+            .truncate(false)
+            .open(heap_file_path)
+    }
+
+    pub fn durability(&self) -> Durability {
+        self.durability
+    }
+
+    pub fn sync_mode(&self) -> SyncMode {
+        self.sync_mode
+    }
+
+    // Opt in to tolerate storages whose sync_all fails as unsupported (e.g. EINVAL on some virtual files)
+    // NOTE: the data is only flushed to the OS then, so it may be lost on crash
+    pub fn set_sync_mode(&mut self, sync_mode: SyncMode) {
+        self.sync_mode = sync_mode;
     }
 
     // allocate new page id
@@ -88,16 +242,59 @@ impl DiskManager {
         // seek for page head
         self.heap_file.seek(SeekFrom::Start(offset))?;
         // write data
-        self.heap_file.write_all(data)
+        self.heap_file.write_all(data)?;
+        self.file_size = self.file_size.max(offset + data.len() as u64);
+        Ok(())
+    }
+
+    // Check that the heap file wasn't truncated behind our back (e.g. by another process),
+    // which would make reads go past EOF
+    pub fn revalidate_size(&mut self) -> Result<(), Error> {
+        let actual = self.heap_file.seek(SeekFrom::End(0))?;
+        if actual < self.file_size {
+            return Err(Error::FileShrunk {
+                expected: self.file_size,
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    // iterate over every page of the heap file without going through the buffer pool
+    // NOTE: an I/O error is yielded for the failed page only, the iteration goes on with the next page
+    pub fn page_iter(
+        &mut self,
+    ) -> impl Iterator<Item = io::Result<(PageId, Box<[u8; PAGE_SIZE]>)>> + '_ {
+        (0..self.next_page_id).map(move |page_id| {
+            let page_id = PageId(page_id);
+            let mut page = Box::new([0u8; PAGE_SIZE]);
+            self.read_page_data(page_id, page.as_mut())?;
+            Ok((page_id, page))
+        })
     }
 
     pub fn sync(&mut self) -> io::Result<()> {
         // NOTE: ? operator early returns an Err(e)
         self.heap_file.flush()?;
-        self.heap_file.sync_all()
+        if self.durability == Durability::Nondurable {
+            return Ok(());
+        }
+        match self.heap_file.sync_all() {
+            // NOTE: the caller opted into this, so the downgrade is silent
+            Err(e) if self.sync_mode == SyncMode::Lenient && is_sync_unsupported(&e) => Ok(()),
+            result => result,
+        }
     }
 }
 
+fn is_sync_unsupported(e: &io::Error) -> bool {
+    // NOTE: EINVAL is reported as InvalidInput
+    matches!(
+        e.kind(),
+        io::ErrorKind::Unsupported | io::ErrorKind::InvalidInput
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -106,7 +303,7 @@ mod tests {
     #[test]
     fn test() {
         let (data_file, data_file_path) = NamedTempFile::new().unwrap().into_parts();
-        let mut disk = DiskManager::new(data_file).unwrap();
+        let mut disk = DiskManager::new_nondurable(data_file).unwrap();
         let mut hello = Vec::with_capacity(PAGE_SIZE);
         hello.extend_from_slice(b"hello");
         hello.resize(PAGE_SIZE, 0);
@@ -118,11 +315,177 @@ mod tests {
         let world_page_id = disk.allocate_page();
         disk.write_page_data(world_page_id, &world).unwrap();
         drop(disk);
-        let mut disk2 = DiskManager::open(&data_file_path).unwrap();
+        let mut disk2 = DiskManager::open_nondurable(&data_file_path).unwrap();
         let mut buf = vec![0; PAGE_SIZE];
         disk2.read_page_data(hello_page_id, &mut buf).unwrap();
         assert_eq!(hello, buf);
         disk2.read_page_data(world_page_id, &mut buf).unwrap();
         assert_eq!(world, buf);
     }
+
+    #[test]
+    fn test_cursor_storage() {
+        // the stream already holds two pages
+        let mut disk =
+            DiskManager::new_nondurable(io::Cursor::new(vec![0u8; PAGE_SIZE * 2])).unwrap();
+        let mut hello = vec![0u8; PAGE_SIZE];
+        hello[..5].copy_from_slice(b"hello");
+        let hello_page_id = disk.allocate_page();
+        assert_eq!(PageId(2), hello_page_id);
+        disk.write_page_data(hello_page_id, &hello).unwrap();
+        let mut world = vec![0u8; PAGE_SIZE];
+        world[..5].copy_from_slice(b"world");
+        disk.write_page_data(PageId(0), &world).unwrap();
+        disk.sync().unwrap();
+        let mut buf = vec![0; PAGE_SIZE];
+        disk.read_page_data(hello_page_id, &mut buf).unwrap();
+        assert_eq!(hello, buf);
+        disk.read_page_data(PageId(0), &mut buf).unwrap();
+        assert_eq!(world, buf);
+    }
+
+    #[test]
+    fn test_from_stream() {
+        // a stream that only implements Read + Write + Seek
+        let mut disk =
+            DiskManager::from_stream(UnsyncableStorage(io::Cursor::new(vec![]))).unwrap();
+        let page_id = disk.allocate_page();
+        disk.write_page_data(page_id, &[1u8; PAGE_SIZE]).unwrap();
+        disk.sync().unwrap();
+        let mut buf = vec![0; PAGE_SIZE];
+        disk.read_page_data(page_id, &mut buf).unwrap();
+        assert_eq!(vec![1u8; PAGE_SIZE], buf);
+
+        // cursors over other buffers work as they are
+        let mut disk =
+            DiskManager::new(io::Cursor::new(vec![0u8; PAGE_SIZE].into_boxed_slice())).unwrap();
+        disk.write_page_data(PageId(0), &[2u8; PAGE_SIZE]).unwrap();
+        disk.read_page_data(PageId(0), &mut buf).unwrap();
+        assert_eq!(vec![2u8; PAGE_SIZE], buf);
+    }
+
+    #[test]
+    fn test_send() {
+        // e.g. to hand the disk manager over to a flusher thread
+        fn assert_send<T: Send>() {}
+        assert_send::<DiskManager>();
+    }
+
+    // Storage whose sync_all is not supported
+    struct UnsyncableStorage(io::Cursor<Vec<u8>>);
+
+    impl Read for UnsyncableStorage {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Write for UnsyncableStorage {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.0.flush()
+        }
+    }
+
+    impl Seek for UnsyncableStorage {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.0.seek(pos)
+        }
+    }
+
+    impl Storage for UnsyncableStorage {
+        fn sync_all(&mut self) -> io::Result<()> {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "sync_all is not supported",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_sync_mode() {
+        let mut disk = DiskManager::new(UnsyncableStorage(io::Cursor::new(vec![]))).unwrap();
+        let page_id = disk.allocate_page();
+        disk.write_page_data(page_id, &[1u8; PAGE_SIZE]).unwrap();
+        assert_eq!(SyncMode::Strict, disk.sync_mode());
+        assert_eq!(io::ErrorKind::Unsupported, disk.sync().unwrap_err().kind());
+        disk.set_sync_mode(SyncMode::Lenient);
+        disk.sync().unwrap();
+        let mut buf = vec![0; PAGE_SIZE];
+        disk.read_page_data(page_id, &mut buf).unwrap();
+        assert_eq!(vec![1u8; PAGE_SIZE], buf);
+    }
+
+    #[test]
+    fn test_revalidate_size() {
+        let (data_file, data_file_path) = NamedTempFile::new().unwrap().into_parts();
+        let mut disk = DiskManager::new_nondurable(data_file).unwrap();
+        for _ in 0..3 {
+            let page_id = disk.allocate_page();
+            disk.write_page_data(page_id, &[1u8; PAGE_SIZE]).unwrap();
+        }
+        // allocated but not written yet
+        disk.allocate_page();
+        disk.revalidate_size().unwrap();
+
+        let other = OpenOptions::new()
+            .write(true)
+            .open(&data_file_path)
+            .unwrap();
+        other.set_len(PAGE_SIZE as u64).unwrap();
+        match disk.revalidate_size() {
+            Err(Error::FileShrunk { expected, actual }) => {
+                assert_eq!(PAGE_SIZE as u64 * 3, expected);
+                assert_eq!(PAGE_SIZE as u64, actual);
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_next_page_id_floor() {
+        let (_data_file, data_file_path) = NamedTempFile::new().unwrap().into_parts();
+        let options = DiskManagerOptions {
+            next_page_id_floor: 1000,
+            ..Default::default()
+        };
+        let mut disk = DiskManager::open_with_options(&data_file_path, options).unwrap();
+        let page_id = disk.allocate_page();
+        assert_eq!(PageId(1000), page_id);
+        disk.write_page_data(page_id, &[1u8; PAGE_SIZE]).unwrap();
+        assert_eq!(PageId(1001), disk.allocate_page());
+        drop(disk);
+
+        // the floor doesn't hold ids back once the file is past it
+        let mut disk = DiskManager::open_with_options(&data_file_path, options).unwrap();
+        assert_eq!(PageId(1001), disk.allocate_page());
+        let mut buf = vec![0; PAGE_SIZE];
+        disk.read_page_data(PageId(1000), &mut buf).unwrap();
+        assert_eq!(vec![1u8; PAGE_SIZE], buf);
+    }
+
+    #[test]
+    fn test_page_iter() {
+        let (data_file, _data_file_path) = NamedTempFile::new().unwrap().into_parts();
+        let mut disk = DiskManager::new_nondurable(data_file).unwrap();
+        let mut expected = vec![];
+        for word in [b"one", b"two", b"tri"] {
+            let mut page = [0u8; PAGE_SIZE];
+            page[..word.len()].copy_from_slice(word);
+            let page_id = disk.allocate_page();
+            disk.write_page_data(page_id, &page).unwrap();
+            expected.push((page_id, page));
+        }
+        let pages: Vec<_> = disk
+            .page_iter()
+            .map(|page| {
+                let (page_id, page) = page.unwrap();
+                (page_id, *page)
+            })
+            .collect();
+        assert_eq!(expected, pages);
+    }
 }
\ No newline at end of file
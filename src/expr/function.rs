@@ -0,0 +1,561 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use super::{eval_binary, type_mismatch, Error};
+use crate::sql::ast::BinaryOp;
+use crate::value::{DataType, Value};
+
+pub type ScalarFn = dyn Fn(&[Value]) -> Result<Value, Error>;
+
+// Scalar function callable from SQL, e.g. UPPER(name)
+pub struct ScalarFunction {
+    name: String,
+    min_args: usize,
+    // None for variadic functions
+    max_args: Option<usize>,
+    // NULL in any argument makes the result NULL without calling the function
+    strict: bool,
+    // types accepted by each argument, checked at bind time where the argument types are known.
+    // The last entry also covers the rest of the arguments. Empty accepts any type
+    arg_types: Vec<Vec<DataType>>,
+    function: Box<ScalarFn>,
+}
+
+impl ScalarFunction {
+    // The function only sees non-NULL arguments, see non_strict
+    pub fn new(
+        name: &str,
+        min_args: usize,
+        max_args: Option<usize>,
+        function: impl Fn(&[Value]) -> Result<Value, Error> + 'static,
+    ) -> Self {
+        Self {
+            name: name.to_ascii_uppercase(),
+            min_args,
+            max_args,
+            strict: true,
+            arg_types: vec![],
+            function: Box::new(function),
+        }
+    }
+
+    // Declare the types each argument accepts, e.g. &[&[DataType::Text], &[DataType::Integer]],
+    // so that bind rejects calls like UPPER(1) instead of failing on every row
+    pub fn with_arg_types(mut self, arg_types: &[&[DataType]]) -> Self {
+        self.arg_types = arg_types.iter().map(|types| types.to_vec()).collect();
+        self
+    }
+
+    // Let the function handle NULL arguments itself
+    pub fn non_strict(mut self) -> Self {
+        self.strict = false;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn check_arity(&self, num_args: usize) -> Result<(), Error> {
+        let expected = match self.max_args {
+            Some(max_args) if max_args == self.min_args => max_args.to_string(),
+            Some(max_args) => format!("{} to {}", self.min_args, max_args),
+            None => format!("at least {}", self.min_args),
+        };
+        if num_args < self.min_args || self.max_args.is_some_and(|max_args| num_args > max_args) {
+            return Err(Error::ArgumentCount {
+                name: self.name.clone(),
+                expected,
+                actual: num_args,
+            });
+        }
+        Ok(())
+    }
+
+    // Check the statically known argument types (None for unknown) against with_arg_types
+    pub fn check_arg_types(&self, arg_types: &[Option<DataType>]) -> Result<(), Error> {
+        for (i, arg_type) in arg_types.iter().enumerate() {
+            let accepted = match self.arg_types.get(i).or(self.arg_types.last()) {
+                Some(accepted) => accepted,
+                None => return Ok(()),
+            };
+            if let Some(arg_type) = arg_type {
+                if !accepted.contains(arg_type) {
+                    return Err(Error::TypeMismatch(format!(
+                        "argument {} of {} can't be {}",
+                        i + 1,
+                        self.name,
+                        arg_type
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn call(&self, args: &[Value]) -> Result<Value, Error> {
+        self.check_arity(args.len())?;
+        if self.strict && args.iter().any(Value::is_null) {
+            return Ok(Value::Null);
+        }
+        (self.function)(args)
+    }
+}
+
+impl fmt::Debug for ScalarFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ScalarFunction({})", self.name)
+    }
+}
+
+// NOTE: a registry holds one function per name, so the name identifies it
+impl PartialEq for ScalarFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+// Functions by name, names are case-insensitive
+pub struct FunctionRegistry {
+    functions: HashMap<String, Rc<ScalarFunction>>,
+}
+
+impl FunctionRegistry {
+    // Registry with the built-in functions
+    pub fn new() -> Self {
+        let mut registry = Self {
+            functions: HashMap::new(),
+        };
+        for function in builtins() {
+            registry.register(function);
+        }
+        registry
+    }
+
+    // Add a function, replacing a function of the same name (built-in ones included)
+    pub fn register(&mut self, function: ScalarFunction) {
+        self.functions
+            .insert(function.name.clone(), Rc::new(function));
+    }
+
+    pub fn get(&self, name: &str) -> Option<Rc<ScalarFunction>> {
+        self.functions.get(&name.to_ascii_uppercase()).cloned()
+    }
+}
+
+impl Default for FunctionRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+thread_local! {
+    static BUILTINS: FunctionRegistry = FunctionRegistry::new();
+}
+
+// Run f with the built-in functions, used when no registry is given
+pub(super) fn with_builtins<R>(f: impl FnOnce(&FunctionRegistry) -> R) -> R {
+    BUILTINS.with(f)
+}
+
+fn builtins() -> Vec<ScalarFunction> {
+    vec![
+        ScalarFunction::new("UPPER", 1, Some(1), |args| match &args[0] {
+            Value::Text(text) => Ok(Value::Text(text.to_uppercase())),
+            value => Err(type_mismatch("UPPER", &[value])),
+        })
+        .with_arg_types(&[&[DataType::Text]]),
+        ScalarFunction::new("LOWER", 1, Some(1), |args| match &args[0] {
+            Value::Text(text) => Ok(Value::Text(text.to_lowercase())),
+            value => Err(type_mismatch("LOWER", &[value])),
+        })
+        .with_arg_types(&[&[DataType::Text]]),
+        // length in characters, not bytes
+        ScalarFunction::new("LENGTH", 1, Some(1), |args| match &args[0] {
+            Value::Text(text) => Ok(Value::Integer(text.chars().count() as i64)),
+            value => Err(type_mismatch("LENGTH", &[value])),
+        })
+        .with_arg_types(&[&[DataType::Text]]),
+        ScalarFunction::new("SUBSTR", 2, Some(3), substr)
+            .with_arg_types(&[&[DataType::Text], &[DataType::Integer]]),
+        // removes leading and trailing spaces
+        ScalarFunction::new("TRIM", 1, Some(1), |args| match &args[0] {
+            Value::Text(text) => Ok(Value::Text(text.trim_matches(' ').to_string())),
+            value => Err(type_mismatch("TRIM", &[value])),
+        })
+        .with_arg_types(&[&[DataType::Text]]),
+        // NOTE: unlike ||, NULL arguments are skipped instead of making the result NULL
+        ScalarFunction::new("CONCAT", 1, None, |args| {
+            let text = args
+                .iter()
+                .filter(|value| !value.is_null())
+                .map(|value| value.to_string())
+                .collect();
+            Ok(Value::Text(text))
+        })
+        .non_strict(),
+        ScalarFunction::new("ABS", 1, Some(1), |args| match &args[0] {
+            Value::Integer(integer) => integer
+                .checked_abs()
+                .map(Value::Integer)
+                .ok_or(Error::Overflow),
+            Value::Float(float) => Ok(Value::Float(float.abs())),
+            value => Err(type_mismatch("ABS", &[value])),
+        })
+        .with_arg_types(&[&[DataType::Integer, DataType::Float]]),
+        ScalarFunction::new("ROUND", 1, Some(2), round)
+            .with_arg_types(&[&[DataType::Integer, DataType::Float], &[DataType::Integer]]),
+        // NULL if both are equal, the first argument otherwise
+        ScalarFunction::new("NULLIF", 2, Some(2), |args| {
+            if args[0].is_null() || args[1].is_null() {
+                return Ok(args[0].clone());
+            }
+            match eval_binary(BinaryOp::Eq, &args[0], &args[1])? {
+                Value::Boolean(true) => Ok(Value::Null),
+                _ => Ok(args[0].clone()),
+            }
+        })
+        .non_strict(),
+    ]
+}
+
+fn operands(args: &[Value]) -> Vec<&Value> {
+    args.iter().collect()
+}
+
+// SUBSTR(text, start[, length]) with 1-based positions.
+// Like the SQL standard, positions before 1 count towards the length: SUBSTR('abc', 0, 2) is 'a'.
+fn substr(args: &[Value]) -> Result<Value, Error> {
+    let (text, start, length) = match args {
+        [Value::Text(text), Value::Integer(start)] => (text, *start, None),
+        [Value::Text(text), Value::Integer(start), Value::Integer(length)] => {
+            (text, *start, Some(*length))
+        }
+        _ => return Err(type_mismatch("SUBSTR", &operands(args))),
+    };
+    let end = match length {
+        Some(length) if length < 0 => {
+            return Err(Error::InvalidArgument(format!(
+                "negative length {} in SUBSTR",
+                length
+            )));
+        }
+        Some(length) => start.saturating_add(length),
+        None => i64::MAX,
+    };
+    let first = start.max(1);
+    let skip = usize::try_from(first - 1).unwrap_or(usize::MAX);
+    let take = usize::try_from((end - first).max(0)).unwrap_or(usize::MAX);
+    Ok(Value::Text(text.chars().skip(skip).take(take).collect()))
+}
+
+// ROUND(number[, digits]), halves are rounded away from zero.
+// Negative digits round to the left of the decimal point: ROUND(1250, -2) is 1300.
+fn round(args: &[Value]) -> Result<Value, Error> {
+    let digits = match args.get(1) {
+        Some(Value::Integer(digits)) => *digits,
+        None => 0,
+        Some(_) => return Err(type_mismatch("ROUND", &operands(args))),
+    };
+    match &args[0] {
+        Value::Integer(integer) if digits >= 0 => Ok(Value::Integer(*integer)),
+        Value::Integer(integer) => {
+            // NOTE: |i64| < 10^19 / 2, so anything rounded to 19 or more digits is 0
+            let factor = match 10i64.checked_pow((-digits).min(19) as u32) {
+                Some(factor) => factor,
+                None => return Ok(Value::Integer(0)),
+            };
+            let remainder = integer % factor;
+            let truncated = integer - remainder;
+            if remainder.unsigned_abs() * 2 >= factor.unsigned_abs() {
+                truncated
+                    .checked_add(remainder.signum() * factor)
+                    .map(Value::Integer)
+                    .ok_or(Error::Overflow)
+            } else {
+                Ok(Value::Integer(truncated))
+            }
+        }
+        Value::Float(float) => {
+            let factor = 10f64.powi(digits.clamp(-308, 308) as i32);
+            let scaled = float * factor;
+            if !scaled.is_finite() || factor == 0.0 {
+                // more digits than a float holds, or rounded beyond its magnitude
+                return Ok(Value::Float(if factor == 0.0 { 0.0 } else { *float }));
+            }
+            Ok(Value::Float(scaled.round() / factor))
+        }
+        _ => Err(type_mismatch("ROUND", &operands(args))),
+    }
+}
+
+// CAST(value AS data_type). Values that can't be represented are an error, never a silent NULL.
+// - FLOAT to INTEGER rounds halves away from zero
+// - TEXT is parsed after trimming spaces, BOOLEAN accepts true/false, t/f, yes/no and 1/0
+// - INTEGER to BOOLEAN is false only for 0
+pub fn cast(value: Value, data_type: DataType) -> Result<Value, Error> {
+    let invalid = |value: &Value| Error::InvalidCast {
+        value: match value {
+            Value::Text(text) => format!("'{}'", text),
+            value => value.to_string(),
+        },
+        data_type,
+    };
+    let result = match (&value, data_type) {
+        (Value::Null, _) => Some(Value::Null),
+        (value, data_type) if value.data_type() == Some(data_type) => Some(value.clone()),
+        (value, DataType::Text) => Some(Value::Text(value.to_string())),
+        (Value::Integer(integer), DataType::Float) => Some(Value::Float(*integer as f64)),
+        (Value::Integer(integer), DataType::Boolean) => Some(Value::Boolean(*integer != 0)),
+        (Value::Float(float), DataType::Integer) => {
+            let rounded = float.round();
+            // NOTE: i64::MAX as f64 rounds up to 2^63, which is already out of range
+            if rounded >= i64::MIN as f64 && rounded < i64::MAX as f64 {
+                Some(Value::Integer(rounded as i64))
+            } else {
+                None
+            }
+        }
+        (Value::Boolean(boolean), DataType::Integer) => Some(Value::Integer(*boolean as i64)),
+        (Value::Text(text), DataType::Integer) => {
+            text.trim_matches(' ').parse().ok().map(Value::Integer)
+        }
+        (Value::Text(text), DataType::Float) => text
+            .trim_matches(' ')
+            .parse::<f64>()
+            .ok()
+            .filter(|float| float.is_finite())
+            .map(Value::Float),
+        (Value::Text(text), DataType::Boolean) => {
+            match text.trim_matches(' ').to_ascii_lowercase().as_str() {
+                "true" | "t" | "yes" | "1" => Some(Value::Boolean(true)),
+                "false" | "f" | "no" | "0" => Some(Value::Boolean(false)),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    result.ok_or_else(|| invalid(&value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::Expr;
+    use crate::sql::parse_expr;
+    use crate::value::{Column, Schema};
+
+    fn schema() -> Schema {
+        Schema::new(vec![
+            Column {
+                name: "s".to_string(),
+                data_type: DataType::Text,
+            },
+            Column {
+                name: "n".to_string(),
+                data_type: DataType::Integer,
+            },
+        ])
+    }
+
+    fn eval(sql: &str) -> Result<Value, Error> {
+        let row = vec![Value::Text("Grüße".to_string()), Value::Null];
+        let expr = Expr::from(parse_expr(sql).unwrap()).bind(&schema())?;
+        expr.eval(&row, &schema())
+    }
+
+    #[test]
+    fn test_builtins() {
+        use Value::*;
+        let text = |text: &str| Ok(Text(text.to_string()));
+        let cases = [
+            ("UPPER(s)", text("GRÜSSE")),
+            ("lower('AbC')", text("abc")),
+            ("LENGTH(s)", Ok(Integer(5))),
+            ("LENGTH(NULL)", Ok(Null)),
+            ("LENGTH(CAST(n AS TEXT))", Ok(Null)),
+            ("SUBSTR(s, 2, 3)", text("rüß")),
+            ("SUBSTR(s, 4)", text("ße")),
+            ("SUBSTR(s, 0, 2)", text("G")),
+            ("SUBSTR(s, 10)", text("")),
+            ("SUBSTR(s, n)", Ok(Null)),
+            (
+                "SUBSTR(s, 1, -1)",
+                Err(Error::InvalidArgument(
+                    "negative length -1 in SUBSTR".to_string(),
+                )),
+            ),
+            ("TRIM('  a b ')", text("a b")),
+            ("CONCAT(s, n, 1, 2.5)", text("Grüße12.5")),
+            ("s || n", Ok(Null)),
+            ("ABS(-3)", Ok(Integer(3))),
+            ("ABS(-2.5)", Ok(Float(2.5))),
+            ("ABS(-9223372036854775807 - 1)", Err(Error::Overflow)),
+            ("ROUND(2.5)", Ok(Float(3.0))),
+            ("ROUND(-2.5)", Ok(Float(-3.0))),
+            ("ROUND(1.2345, 2)", Ok(Float(1.23))),
+            ("ROUND(1250, -2)", Ok(Integer(1300))),
+            ("ROUND(-1249, -2)", Ok(Integer(-1200))),
+            ("ROUND(12, -30)", Ok(Integer(0))),
+            ("ROUND(9223372036854775807, -1)", Err(Error::Overflow)),
+            ("NULLIF(1, 1)", Ok(Null)),
+            ("NULLIF(1, 2.0)", Ok(Integer(1))),
+            ("NULLIF(1, n)", Ok(Integer(1))),
+            ("NULLIF(n, 1)", Ok(Null)),
+            ("COALESCE(n, NULL, 3, 1 / 0)", Ok(Integer(3))),
+            ("COALESCE(n)", Ok(Null)),
+            (
+                "UPPER(1)",
+                Err(Error::TypeMismatch(
+                    "argument 1 of UPPER can't be INTEGER".to_string(),
+                )),
+            ),
+            // argument types are checked at bind time where they are known, at run time otherwise
+            (
+                "UPPER(-LENGTH(s))",
+                Err(Error::TypeMismatch(
+                    "UPPER is not defined for INTEGER".to_string(),
+                )),
+            ),
+            (
+                "UPPER(n)",
+                Err(Error::TypeMismatch(
+                    "argument 1 of UPPER can't be INTEGER".to_string(),
+                )),
+            ),
+            (
+                "SUBSTR(s, 1, 2.5)",
+                Err(Error::TypeMismatch(
+                    "argument 3 of SUBSTR can't be FLOAT".to_string(),
+                )),
+            ),
+            (
+                "ROUND(s = 'x')",
+                Err(Error::TypeMismatch(
+                    "argument 1 of ROUND can't be BOOLEAN".to_string(),
+                )),
+            ),
+            (
+                "missing(s)",
+                Err(Error::UnknownFunction("missing".to_string())),
+            ),
+            (
+                "SUBSTR(s)",
+                Err(Error::ArgumentCount {
+                    name: "SUBSTR".to_string(),
+                    expected: "2 to 3".to_string(),
+                    actual: 1,
+                }),
+            ),
+            (
+                "COALESCE()",
+                Err(Error::ArgumentCount {
+                    name: "COALESCE".to_string(),
+                    expected: "at least 1".to_string(),
+                    actual: 0,
+                }),
+            ),
+        ];
+        for (sql, expected) in cases {
+            assert_eq!(expected, eval(sql), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_cast() {
+        use DataType::*;
+        let cases = [
+            (Value::Null, Integer, Ok(Value::Null)),
+            (Value::Float(2.5), Integer, Ok(Value::Integer(3))),
+            (Value::Float(-2.5), Integer, Ok(Value::Integer(-3))),
+            (
+                Value::Text(" 42 ".to_string()),
+                Integer,
+                Ok(Value::Integer(42)),
+            ),
+            (Value::Boolean(true), Integer, Ok(Value::Integer(1))),
+            (Value::Integer(2), Float, Ok(Value::Float(2.0))),
+            (
+                Value::Text("1e3".to_string()),
+                Float,
+                Ok(Value::Float(1000.0)),
+            ),
+            (Value::Float(2.5), Text, Ok(Value::Text("2.5".to_string()))),
+            (Value::Integer(0), Boolean, Ok(Value::Boolean(false))),
+            (
+                Value::Text("Yes".to_string()),
+                Boolean,
+                Ok(Value::Boolean(true)),
+            ),
+        ];
+        for (value, data_type, expected) in cases {
+            assert_eq!(
+                expected,
+                cast(value.clone(), data_type),
+                "{} AS {}",
+                value,
+                data_type
+            );
+        }
+        let invalid = |value: &str, data_type| {
+            Err(Error::InvalidCast {
+                value: value.to_string(),
+                data_type,
+            })
+        };
+        assert_eq!(
+            invalid("'abc'", Integer),
+            cast(Value::Text("abc".to_string()), Integer)
+        );
+        assert_eq!(
+            invalid("10000000000000000000", Integer),
+            cast(Value::Float(1e19), Integer)
+        );
+        assert_eq!(
+            invalid("'inf'", Float),
+            cast(Value::Text("inf".to_string()), Float)
+        );
+        assert_eq!(invalid("1.5", Boolean), cast(Value::Float(1.5), Boolean));
+        assert_eq!(
+            Ok(Value::Integer(6)),
+            eval("CAST(SUBSTR('x5', 2) AS INTEGER) + 1")
+        );
+        assert_eq!(Ok(Value::Null), eval("CAST(n AS TEXT)"));
+    }
+
+    #[test]
+    fn test_register() {
+        let mut functions = FunctionRegistry::new();
+        functions.register(
+            ScalarFunction::new("twice", 1, Some(1), |args| match &args[0] {
+                Value::Integer(integer) => integer
+                    .checked_mul(2)
+                    .map(Value::Integer)
+                    .ok_or(Error::Overflow),
+                value => Err(type_mismatch("TWICE", &[value])),
+            })
+            .with_arg_types(&[&[DataType::Integer]]),
+        );
+        let expr = Expr::from(parse_expr("TWICE(LENGTH(s)) + 1").unwrap());
+        let bound = expr.bind_with_functions(&schema(), &functions).unwrap();
+        let row = vec![Value::Text("abc".to_string()), Value::Null];
+        assert_eq!(Ok(Value::Integer(7)), bound.eval(&row, &schema()));
+        // strict: NULL arguments never reach the function
+        let bound = Expr::from(parse_expr("twice(n)").unwrap())
+            .bind_with_functions(&schema(), &functions)
+            .unwrap();
+        assert_eq!(Ok(Value::Null), bound.eval(&row, &schema()));
+        assert_eq!(
+            Err(Error::TypeMismatch(
+                "argument 1 of TWICE can't be TEXT".to_string()
+            )),
+            Expr::from(parse_expr("twice(s)").unwrap()).bind_with_functions(&schema(), &functions)
+        );
+        // only known to the registry it was registered in
+        assert_eq!(
+            Err(Error::UnknownFunction("TWICE".to_string())),
+            expr.bind(&schema())
+        );
+    }
+}
@@ -0,0 +1,1135 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::ops::Not;
+use std::rc::Rc;
+
+use crate::sql::ast::{self, BinaryOp, UnaryOp};
+use crate::value::{DataType, Row, Schema, Value};
+
+mod function;
+
+pub use function::{cast, FunctionRegistry, ScalarFn, ScalarFunction};
+
+// Expression evaluator over Row values.
+// - NULL follows SQL three-valued logic: it propagates through operators, except that
+//   `NULL AND FALSE` is FALSE and `NULL OR TRUE` is TRUE.
+// - INTEGER and FLOAT mix by converting the INTEGER to FLOAT, except comparisons, which are exact.
+// - Division (and modulo) by zero is an error rather than NULL, so bad data doesn't silently vanish.
+// - INTEGER arithmetic is checked, overflowing is an error instead of wrapping.
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+    #[error("unknown column {0}")]
+    UnknownColumn(String),
+    #[error("column index {0} is out of range")]
+    ColumnOutOfRange(usize),
+    #[error("type mismatch: {0}")]
+    TypeMismatch(String),
+    #[error("division by zero")]
+    DivisionByZero,
+    #[error("integer overflow")]
+    Overflow,
+    #[error("invalid LIKE pattern: {0}")]
+    InvalidPattern(String),
+    #[error("unknown function {0}")]
+    UnknownFunction(String),
+    #[error("{name} takes {expected} arguments but {actual} were given")]
+    ArgumentCount {
+        name: String,
+        expected: String,
+        actual: usize,
+    },
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error("cannot cast {value} to {data_type}")]
+    InvalidCast { value: String, data_type: DataType },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Value),
+    // column referenced by name, looked up in the schema on every evaluation
+    Column(String),
+    // column resolved by bind
+    ColumnIndex(usize),
+    Unary {
+        op: UnaryOp,
+        expr: Box<Expr>,
+    },
+    Binary {
+        left: Box<Expr>,
+        op: BinaryOp,
+        right: Box<Expr>,
+    },
+    IsNull {
+        expr: Box<Expr>,
+        negated: bool,
+    },
+    InList {
+        expr: Box<Expr>,
+        list: Vec<Expr>,
+        negated: bool,
+    },
+    Between {
+        expr: Box<Expr>,
+        low: Box<Expr>,
+        high: Box<Expr>,
+        negated: bool,
+    },
+    Like {
+        expr: Box<Expr>,
+        pattern: Box<Expr>,
+        escape: Option<Box<Expr>>,
+        negated: bool,
+    },
+    // function referenced by name, looked up in the built-in functions on every evaluation
+    Function {
+        name: String,
+        args: Vec<Expr>,
+    },
+    // function resolved by bind
+    Call {
+        function: Rc<ScalarFunction>,
+        args: Vec<Expr>,
+    },
+    // COALESCE is not a function: it only evaluates arguments up to the first non-NULL one
+    Coalesce(Vec<Expr>),
+    Cast {
+        expr: Box<Expr>,
+        data_type: DataType,
+    },
+}
+
+impl From<ast::Expr> for Expr {
+    fn from(expr: ast::Expr) -> Self {
+        match expr {
+            ast::Expr::Literal(literal) => Expr::Literal(literal.into()),
+            ast::Expr::Column(name) => Expr::Column(name),
+            ast::Expr::Unary { op, expr } => Expr::Unary {
+                op,
+                expr: Box::new((*expr).into()),
+            },
+            ast::Expr::Binary { left, op, right } => Expr::Binary {
+                left: Box::new((*left).into()),
+                op,
+                right: Box::new((*right).into()),
+            },
+            ast::Expr::IsNull { expr, negated } => Expr::IsNull {
+                expr: Box::new((*expr).into()),
+                negated,
+            },
+            ast::Expr::InList {
+                expr,
+                list,
+                negated,
+            } => Expr::InList {
+                expr: Box::new((*expr).into()),
+                list: list.into_iter().map(Expr::from).collect(),
+                negated,
+            },
+            ast::Expr::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => Expr::Between {
+                expr: Box::new((*expr).into()),
+                low: Box::new((*low).into()),
+                high: Box::new((*high).into()),
+                negated,
+            },
+            ast::Expr::Like {
+                expr,
+                pattern,
+                escape,
+                negated,
+            } => Expr::Like {
+                expr: Box::new((*expr).into()),
+                pattern: Box::new((*pattern).into()),
+                escape: escape.map(|escape| Box::new((*escape).into())),
+                negated,
+            },
+            ast::Expr::Function { name, args } => {
+                let args = args.into_iter().map(Expr::from).collect();
+                if name.eq_ignore_ascii_case("COALESCE") {
+                    return Expr::Coalesce(args);
+                }
+                Expr::Function { name, args }
+            }
+            ast::Expr::Cast { expr, data_type } => Expr::Cast {
+                expr: Box::new((*expr).into()),
+                data_type,
+            },
+        }
+    }
+}
+
+impl From<ast::Literal> for Value {
+    fn from(literal: ast::Literal) -> Self {
+        match literal {
+            ast::Literal::Null => Value::Null,
+            ast::Literal::Boolean(boolean) => Value::Boolean(boolean),
+            ast::Literal::Integer(integer) => Value::Integer(integer),
+            ast::Literal::Float(float) => Value::Float(float),
+            ast::Literal::String(string) => Value::Text(string),
+        }
+    }
+}
+
+impl Expr {
+    // Resolve column names to indexes and function names to functions once,
+    // so that evaluating per row doesn't look up names
+    pub fn bind(&self, schema: &Schema) -> Result<Expr, Error> {
+        function::with_builtins(|functions| self.bind_with_functions(schema, functions))
+    }
+
+    // bind, with the functions from a registry instead of the built-in ones
+    pub fn bind_with_functions(
+        &self,
+        schema: &Schema,
+        functions: &FunctionRegistry,
+    ) -> Result<Expr, Error> {
+        let bind = |expr: &Expr| expr.bind_with_functions(schema, functions);
+        let bind_all = |exprs: &[Expr]| exprs.iter().map(bind).collect::<Result<Vec<_>, _>>();
+        let expr = match self {
+            Expr::Column(name) => {
+                let index = schema
+                    .index_of(name)
+                    .ok_or_else(|| Error::UnknownColumn(name.clone()))?;
+                Expr::ColumnIndex(index)
+            }
+            Expr::Literal(_) | Expr::ColumnIndex(_) => self.clone(),
+            Expr::Unary { op, expr } => Expr::Unary {
+                op: *op,
+                expr: Box::new(bind(expr)?),
+            },
+            Expr::Binary { left, op, right } => Expr::Binary {
+                left: Box::new(bind(left)?),
+                op: *op,
+                right: Box::new(bind(right)?),
+            },
+            Expr::IsNull { expr, negated } => Expr::IsNull {
+                expr: Box::new(bind(expr)?),
+                negated: *negated,
+            },
+            Expr::InList {
+                expr,
+                list,
+                negated,
+            } => Expr::InList {
+                expr: Box::new(bind(expr)?),
+                list: bind_all(list)?,
+                negated: *negated,
+            },
+            Expr::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => Expr::Between {
+                expr: Box::new(bind(expr)?),
+                low: Box::new(bind(low)?),
+                high: Box::new(bind(high)?),
+                negated: *negated,
+            },
+            Expr::Like {
+                expr,
+                pattern,
+                escape,
+                negated,
+            } => Expr::Like {
+                expr: Box::new(bind(expr)?),
+                pattern: Box::new(bind(pattern)?),
+                escape: match escape {
+                    Some(escape) => Some(Box::new(bind(escape)?)),
+                    None => None,
+                },
+                negated: *negated,
+            },
+            Expr::Function { name, args } => {
+                let function = functions
+                    .get(name)
+                    .ok_or_else(|| Error::UnknownFunction(name.clone()))?;
+                function.check_arity(args.len())?;
+                let args = bind_all(args)?;
+                let arg_types: Vec<_> = args.iter().map(|arg| arg.static_type(schema)).collect();
+                function.check_arg_types(&arg_types)?;
+                Expr::Call { function, args }
+            }
+            Expr::Call { function, args } => {
+                let args = bind_all(args)?;
+                let arg_types: Vec<_> = args.iter().map(|arg| arg.static_type(schema)).collect();
+                function.check_arg_types(&arg_types)?;
+                Expr::Call {
+                    function: function.clone(),
+                    args,
+                }
+            }
+            Expr::Coalesce(args) => {
+                if args.is_empty() {
+                    return Err(Error::ArgumentCount {
+                        name: "COALESCE".to_string(),
+                        expected: "at least 1".to_string(),
+                        actual: 0,
+                    });
+                }
+                Expr::Coalesce(bind_all(args)?)
+            }
+            Expr::Cast { expr, data_type } => Expr::Cast {
+                expr: Box::new(bind(expr)?),
+                data_type: *data_type,
+            },
+        };
+        Ok(expr)
+    }
+
+    // Type of the bound expression's value when it is known without evaluating it.
+    // None if it depends on the row, or if the expression is always NULL
+    fn static_type(&self, schema: &Schema) -> Option<DataType> {
+        match self {
+            Expr::Literal(value) => value.data_type(),
+            Expr::ColumnIndex(index) => schema.columns.get(*index).map(|column| column.data_type),
+            Expr::Cast { data_type, .. } => Some(*data_type),
+            Expr::Unary {
+                op: UnaryOp::Not, ..
+            }
+            | Expr::IsNull { .. }
+            | Expr::InList { .. }
+            | Expr::Between { .. }
+            | Expr::Like { .. } => Some(DataType::Boolean),
+            Expr::Binary { op, .. } => match op {
+                BinaryOp::Eq
+                | BinaryOp::NotEq
+                | BinaryOp::Lt
+                | BinaryOp::LtEq
+                | BinaryOp::Gt
+                | BinaryOp::GtEq
+                | BinaryOp::And
+                | BinaryOp::Or => Some(DataType::Boolean),
+                BinaryOp::Concat => Some(DataType::Text),
+                BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => {
+                    None
+                }
+            },
+            _ => None,
+        }
+    }
+
+    // Evaluate a predicate, whose result must be BOOLEAN or NULL
+    pub fn eval_predicate(&self, row: &Row, schema: &Schema) -> Result<TriBool, Error> {
+        match self.eval_cow(row, schema)?.as_ref() {
+            Value::Null => Ok(TriBool::Unknown),
+            &Value::Boolean(boolean) => Ok(boolean.into()),
+            value => Err(Error::TypeMismatch(format!(
+                "predicate must be BOOLEAN but is {}",
+                value.data_type().unwrap()
+            ))),
+        }
+    }
+
+    pub fn eval(&self, row: &Row, schema: &Schema) -> Result<Value, Error> {
+        self.eval_cow(row, schema).map(Cow::into_owned)
+    }
+
+    // Literals and columns are borrowed rather than cloned,
+    // so that e.g. comparing a TEXT column to a literal doesn't allocate per row
+    fn eval_cow<'a>(&'a self, row: &'a Row, schema: &Schema) -> Result<Cow<'a, Value>, Error> {
+        let value = match self {
+            Expr::Literal(value) => return Ok(Cow::Borrowed(value)),
+            Expr::Column(name) => {
+                let index = schema
+                    .index_of(name)
+                    .ok_or_else(|| Error::UnknownColumn(name.clone()))?;
+                let value = row.get(index).ok_or(Error::ColumnOutOfRange(index))?;
+                return Ok(Cow::Borrowed(value));
+            }
+            Expr::ColumnIndex(index) => {
+                let value = row.get(*index).ok_or(Error::ColumnOutOfRange(*index))?;
+                return Ok(Cow::Borrowed(value));
+            }
+            Expr::Unary { op, expr } => eval_unary(*op, &*expr.eval_cow(row, schema)?)?,
+            Expr::Binary {
+                left,
+                op: BinaryOp::And,
+                right,
+            } => {
+                let left = TriBool::from_value(left.eval(row, schema)?, "AND")?;
+                // NOTE: FALSE AND anything is FALSE
+                if left == TriBool::False {
+                    return Ok(Cow::Owned(Value::Boolean(false)));
+                }
+                let right = TriBool::from_value(right.eval(row, schema)?, "AND")?;
+                left.and(right).into()
+            }
+            Expr::Binary {
+                left,
+                op: BinaryOp::Or,
+                right,
+            } => {
+                let left = TriBool::from_value(left.eval(row, schema)?, "OR")?;
+                // NOTE: TRUE OR anything is TRUE
+                if left == TriBool::True {
+                    return Ok(Cow::Owned(Value::Boolean(true)));
+                }
+                let right = TriBool::from_value(right.eval(row, schema)?, "OR")?;
+                left.or(right).into()
+            }
+            Expr::Binary { left, op, right } => eval_binary(
+                *op,
+                &*left.eval_cow(row, schema)?,
+                &*right.eval_cow(row, schema)?,
+            )?,
+            Expr::IsNull { expr, negated } => {
+                let is_null = expr.eval_cow(row, schema)?.is_null();
+                Value::Boolean(is_null != *negated)
+            }
+            // NULL if nothing matches but a member is NULL, because the NULL might have matched
+            Expr::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                let value = expr.eval_cow(row, schema)?;
+                if value.is_null() {
+                    return Ok(Cow::Owned(Value::Null));
+                }
+                // x IN (a, b) is x = a OR x = b
+                let mut result = TriBool::False;
+                for item in list {
+                    let eq = eval_binary(BinaryOp::Eq, &value, &*item.eval_cow(row, schema)?)?;
+                    result = result.or(TriBool::from_value(eq, "IN")?);
+                    if result == TriBool::True {
+                        break;
+                    }
+                }
+                if *negated {
+                    return Ok(Cow::Owned((!result).into()));
+                }
+                result.into()
+            }
+            // low <= expr AND expr <= high
+            Expr::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => {
+                let value = expr.eval_cow(row, schema)?;
+                let low = eval_binary(BinaryOp::GtEq, &value, &*low.eval_cow(row, schema)?)?;
+                let high = eval_binary(BinaryOp::LtEq, &value, &*high.eval_cow(row, schema)?)?;
+                let result =
+                    TriBool::from_value(low, "BETWEEN")?.and(TriBool::from_value(high, "BETWEEN")?);
+                if *negated {
+                    return Ok(Cow::Owned((!result).into()));
+                }
+                result.into()
+            }
+            Expr::Like {
+                expr,
+                pattern,
+                escape,
+                negated,
+            } => {
+                let value = expr.eval_cow(row, schema)?;
+                let pattern = pattern.eval_cow(row, schema)?;
+                let escape = match escape {
+                    Some(escape) => escape.eval_cow(row, schema)?,
+                    None => Cow::Owned(Value::Text(String::new())),
+                };
+                let (text, pattern, escape) =
+                    match (value.as_ref(), pattern.as_ref(), escape.as_ref()) {
+                        (Value::Null, _, _) | (_, Value::Null, _) | (_, _, Value::Null) => {
+                            return Ok(Cow::Owned(Value::Null))
+                        }
+                        (Value::Text(text), Value::Text(pattern), Value::Text(escape)) => {
+                            (text, pattern, escape)
+                        }
+                        (Value::Text(_), Value::Text(_), escape) => {
+                            return Err(type_mismatch("ESCAPE", &[escape]))
+                        }
+                        _ => return Err(type_mismatch("LIKE", &[&value, &pattern])),
+                    };
+                let mut escape_chars = escape.chars();
+                let escape = match (escape_chars.next(), escape_chars.next()) {
+                    (escape, None) => escape,
+                    _ => {
+                        return Err(Error::InvalidPattern(format!(
+                            "escape '{}' is not a single character",
+                            escape
+                        )))
+                    }
+                };
+                let tokens = like_tokens(pattern, escape)?;
+                let text: Vec<char> = text.chars().collect();
+                Value::Boolean(like_match(&text, &tokens) != *negated)
+            }
+            Expr::Function { name, args } => {
+                let function = function::with_builtins(|functions| functions.get(name))
+                    .ok_or_else(|| Error::UnknownFunction(name.clone()))?;
+                let args = args
+                    .iter()
+                    .map(|arg| arg.eval(row, schema))
+                    .collect::<Result<Vec<_>, _>>()?;
+                function.call(&args)?
+            }
+            Expr::Call { function, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.eval(row, schema))
+                    .collect::<Result<Vec<_>, _>>()?;
+                function.call(&args)?
+            }
+            Expr::Coalesce(args) => {
+                for arg in args {
+                    let value = arg.eval_cow(row, schema)?;
+                    if !value.is_null() {
+                        return Ok(value);
+                    }
+                }
+                Value::Null
+            }
+            Expr::Cast { expr, data_type } => cast(expr.eval(row, schema)?, *data_type)?,
+        };
+        Ok(Cow::Owned(value))
+    }
+}
+
+// Truth value of SQL's three-valued logic, NULL is Unknown.
+// Predicates evaluate to it, and each context decides what Unknown means:
+// WHERE, join conditions and HAVING reject it (matches), CHECK constraints accept it (passes_check).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum TriBool {
+    True,
+    False,
+    Unknown,
+}
+
+impl TriBool {
+    // BOOLEAN or NULL, anything else is a type mismatch for op
+    pub fn from_value(value: Value, op: &str) -> Result<TriBool, Error> {
+        match value {
+            Value::Null => Ok(TriBool::Unknown),
+            Value::Boolean(boolean) => Ok(boolean.into()),
+            value => Err(type_mismatch(op, &[&value])),
+        }
+    }
+
+    pub fn and(self, other: TriBool) -> TriBool {
+        match (self, other) {
+            (TriBool::False, _) | (_, TriBool::False) => TriBool::False,
+            (TriBool::True, TriBool::True) => TriBool::True,
+            _ => TriBool::Unknown,
+        }
+    }
+
+    pub fn or(self, other: TriBool) -> TriBool {
+        match (self, other) {
+            (TriBool::True, _) | (_, TriBool::True) => TriBool::True,
+            (TriBool::False, TriBool::False) => TriBool::False,
+            _ => TriBool::Unknown,
+        }
+    }
+
+    // interpretation for WHERE, join conditions and HAVING
+    pub fn matches(self) -> bool {
+        self == TriBool::True
+    }
+
+    // interpretation for CHECK constraints, which only fail on FALSE
+    pub fn passes_check(self) -> bool {
+        self != TriBool::False
+    }
+}
+
+impl Not for TriBool {
+    type Output = TriBool;
+
+    fn not(self) -> TriBool {
+        match self {
+            TriBool::True => TriBool::False,
+            TriBool::False => TriBool::True,
+            TriBool::Unknown => TriBool::Unknown,
+        }
+    }
+}
+
+impl From<bool> for TriBool {
+    fn from(boolean: bool) -> Self {
+        if boolean {
+            TriBool::True
+        } else {
+            TriBool::False
+        }
+    }
+}
+
+impl From<TriBool> for Value {
+    fn from(tri_bool: TriBool) -> Self {
+        match tri_bool {
+            TriBool::True => Value::Boolean(true),
+            TriBool::False => Value::Boolean(false),
+            TriBool::Unknown => Value::Null,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum LikeToken {
+    // %
+    Any,
+    // _
+    One,
+    Char(char),
+}
+
+fn like_tokens(pattern: &str, escape: Option<char>) -> Result<Vec<LikeToken>, Error> {
+    let mut tokens = vec![];
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if Some(c) == escape => match chars.next() {
+                Some(c) => LikeToken::Char(c),
+                None => {
+                    return Err(Error::InvalidPattern(format!(
+                        "'{}' ends with the escape character",
+                        pattern
+                    )))
+                }
+            },
+            '%' => LikeToken::Any,
+            '_' => LikeToken::One,
+            c => LikeToken::Char(c),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+// Iterative matcher that backtracks only to the last %, so it runs in O(text * pattern) at worst
+// (a recursive matcher is exponential on patterns like '%a%a%a%b')
+fn like_match(text: &[char], pattern: &[LikeToken]) -> bool {
+    let (mut t, mut p) = (0, 0);
+    // position of the last % and the text position it is currently matched up to
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(LikeToken::One) => {
+                t += 1;
+                p += 1;
+            }
+            Some(LikeToken::Char(c)) if *c == text[t] => {
+                t += 1;
+                p += 1;
+            }
+            Some(LikeToken::Any) => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            _ => match backtrack {
+                // let the % swallow one more character
+                Some((any_p, any_t)) => {
+                    backtrack = Some((any_p, any_t + 1));
+                    p = any_p + 1;
+                    t = any_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|token| *token == LikeToken::Any)
+}
+
+fn type_mismatch(op: &str, operands: &[&Value]) -> Error {
+    let types: Vec<String> = operands
+        .iter()
+        .map(|value| {
+            value
+                .data_type()
+                .map_or("NULL".to_string(), |data_type| data_type.to_string())
+        })
+        .collect();
+    Error::TypeMismatch(format!("{} is not defined for {}", op, types.join(" and ")))
+}
+
+fn eval_unary(op: UnaryOp, value: &Value) -> Result<Value, Error> {
+    match (op, value) {
+        (_, Value::Null) => Ok(Value::Null),
+        (UnaryOp::Not, Value::Boolean(boolean)) => Ok(Value::Boolean(!boolean)),
+        (UnaryOp::Minus, Value::Integer(integer)) => integer
+            .checked_neg()
+            .map(Value::Integer)
+            .ok_or(Error::Overflow),
+        (UnaryOp::Minus, Value::Float(float)) => Ok(Value::Float(-float)),
+        (UnaryOp::Not, value) => Err(type_mismatch("NOT", &[value])),
+        (UnaryOp::Minus, value) => Err(type_mismatch("-", &[value])),
+    }
+}
+
+fn eval_binary(op: BinaryOp, left: &Value, right: &Value) -> Result<Value, Error> {
+    if left.is_null() || right.is_null() {
+        return Ok(Value::Null);
+    }
+    match op {
+        BinaryOp::Eq
+        | BinaryOp::NotEq
+        | BinaryOp::Lt
+        | BinaryOp::LtEq
+        | BinaryOp::Gt
+        | BinaryOp::GtEq => {
+            let ordering =
+                compare(left, right).ok_or_else(|| type_mismatch(op.as_str(), &[left, right]))?;
+            let result = match op {
+                BinaryOp::Eq => ordering == Ordering::Equal,
+                BinaryOp::NotEq => ordering != Ordering::Equal,
+                BinaryOp::Lt => ordering == Ordering::Less,
+                BinaryOp::LtEq => ordering != Ordering::Greater,
+                BinaryOp::Gt => ordering == Ordering::Greater,
+                _ => ordering != Ordering::Less,
+            };
+            Ok(Value::Boolean(result))
+        }
+        BinaryOp::Concat => match (left, right) {
+            (Value::Text(_), _) | (_, Value::Text(_)) => {
+                Ok(Value::Text(format!("{}{}", left, right)))
+            }
+            _ => Err(type_mismatch("||", &[left, right])),
+        },
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => {
+            eval_arithmetic(op, left, right)
+        }
+        // short-circuited in Expr::eval
+        BinaryOp::And | BinaryOp::Or => unreachable!(),
+    }
+}
+
+// Ordering of two non-NULL values, None when they aren't comparable
+pub fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Integer(l), Value::Integer(r)) => Some(l.cmp(r)),
+        (Value::Integer(l), Value::Float(r)) => compare_integer_float(*l, *r),
+        (Value::Float(l), Value::Integer(r)) => {
+            compare_integer_float(*r, *l).map(Ordering::reverse)
+        }
+        (Value::Float(l), Value::Float(r)) => l.partial_cmp(r),
+        (Value::Text(l), Value::Text(r)) => Some(l.cmp(r)),
+        (Value::Boolean(l), Value::Boolean(r)) => Some(l.cmp(r)),
+        _ => None,
+    }
+}
+
+// NOTE: converting the INTEGER to FLOAT would round it above 2^53,
+//       so the integral part of the FLOAT is compared as INTEGER instead
+fn compare_integer_float(integer: i64, float: f64) -> Option<Ordering> {
+    // 2^63, the first FLOAT above i64::MAX
+    const LIMIT: f64 = 9223372036854775808.0;
+    if float.is_nan() {
+        return None;
+    }
+    if float >= LIMIT {
+        return Some(Ordering::Less);
+    }
+    if float < -LIMIT {
+        return Some(Ordering::Greater);
+    }
+    let integral = float.trunc();
+    // the fraction decides between equal integral parts
+    let ordering = integer
+        .cmp(&(integral as i64))
+        .then_with(|| integral.partial_cmp(&float).unwrap());
+    Some(ordering)
+}
+
+fn eval_arithmetic(op: BinaryOp, left: &Value, right: &Value) -> Result<Value, Error> {
+    match (left, right) {
+        (Value::Integer(l), Value::Integer(r)) => {
+            let (l, r) = (*l, *r);
+            if matches!(op, BinaryOp::Div | BinaryOp::Mod) && r == 0 {
+                return Err(Error::DivisionByZero);
+            }
+            let result = match op {
+                BinaryOp::Add => l.checked_add(r),
+                BinaryOp::Sub => l.checked_sub(r),
+                BinaryOp::Mul => l.checked_mul(r),
+                BinaryOp::Div => l.checked_div(r),
+                _ => l.checked_rem(r),
+            };
+            result.map(Value::Integer).ok_or(Error::Overflow)
+        }
+        (Value::Integer(_), Value::Float(_))
+        | (Value::Float(_), Value::Integer(_))
+        | (Value::Float(_), Value::Float(_)) => {
+            let (l, r) = (as_float(left), as_float(right));
+            if matches!(op, BinaryOp::Div | BinaryOp::Mod) && r == 0.0 {
+                return Err(Error::DivisionByZero);
+            }
+            let result = match op {
+                BinaryOp::Add => l + r,
+                BinaryOp::Sub => l - r,
+                BinaryOp::Mul => l * r,
+                BinaryOp::Div => l / r,
+                _ => l % r,
+            };
+            Ok(Value::Float(result))
+        }
+        _ => Err(type_mismatch(op.as_str(), &[left, right])),
+    }
+}
+
+fn as_float(value: &Value) -> f64 {
+    match value {
+        Value::Integer(integer) => *integer as f64,
+        Value::Float(float) => *float,
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::parse_expr;
+    use crate::value::{Column, DataType};
+
+    fn schema() -> Schema {
+        let column = |name: &str, data_type| Column {
+            name: name.to_string(),
+            data_type,
+        };
+        Schema::new(vec![
+            column("a", DataType::Integer),
+            column("b", DataType::Float),
+            column("s", DataType::Text),
+            column("t", DataType::Boolean),
+            column("n", DataType::Integer),
+        ])
+    }
+
+    fn row() -> Row {
+        vec![
+            Value::Integer(1),
+            Value::Float(2.5),
+            Value::Text("x".to_string()),
+            Value::Boolean(true),
+            Value::Null,
+        ]
+    }
+
+    #[test]
+    fn test_eval() {
+        use Value::*;
+        let cases = [
+            // literals and columns
+            ("1", Ok(Integer(1))),
+            ("'it''s'", Ok(Text("it's".to_string()))),
+            ("A", Ok(Integer(1))),
+            ("n", Ok(Null)),
+            ("missing", Err(Error::UnknownColumn("missing".to_string()))),
+            // arithmetic and coercion
+            ("a + 2 * 3", Ok(Integer(7))),
+            ("7 / 2", Ok(Integer(3))),
+            ("7 % 3", Ok(Integer(1))),
+            ("7.0 / 2", Ok(Float(3.5))),
+            ("a + b", Ok(Float(3.5))),
+            ("-a", Ok(Integer(-1))),
+            ("-b", Ok(Float(-2.5))),
+            ("1 / 0", Err(Error::DivisionByZero)),
+            ("1 % 0", Err(Error::DivisionByZero)),
+            ("b / 0", Err(Error::DivisionByZero)),
+            ("9223372036854775807 + 1", Err(Error::Overflow)),
+            ("-9223372036854775807 - 2", Err(Error::Overflow)),
+            ("4611686018427387904 * 2", Err(Error::Overflow)),
+            ("-(-9223372036854775807 - 1)", Err(Error::Overflow)),
+            ("(-9223372036854775807 - 1) / -1", Err(Error::Overflow)),
+            (
+                "s + 1",
+                Err(Error::TypeMismatch(
+                    "+ is not defined for TEXT and INTEGER".to_string(),
+                )),
+            ),
+            (
+                "-s",
+                Err(Error::TypeMismatch("- is not defined for TEXT".to_string())),
+            ),
+            // concatenation
+            ("s || 'y'", Ok(Text("xy".to_string()))),
+            ("s || a", Ok(Text("x1".to_string()))),
+            (
+                "a || a",
+                Err(Error::TypeMismatch(
+                    "|| is not defined for INTEGER and INTEGER".to_string(),
+                )),
+            ),
+            // comparison
+            ("a = 1", Ok(Boolean(true))),
+            ("a <> 1", Ok(Boolean(false))),
+            ("a < b", Ok(Boolean(true))),
+            ("2.5 <= b", Ok(Boolean(true))),
+            ("1 = 1.0", Ok(Boolean(true))),
+            // INTEGER and FLOAT are compared exactly, not through a rounding conversion
+            ("9007199254740993 = 9007199254740992.0", Ok(Boolean(false))),
+            ("9007199254740993 > 9007199254740992.0", Ok(Boolean(true))),
+            ("9007199254740992.0 < 9007199254740993", Ok(Boolean(true))),
+            ("-2 < -1.5", Ok(Boolean(true))),
+            ("-1.5 < -1", Ok(Boolean(true))),
+            (
+                "9223372036854775807 < 9223372036854775808.0",
+                Ok(Boolean(true)),
+            ),
+            ("'abc' > 'abd'", Ok(Boolean(false))),
+            ("TRUE > FALSE", Ok(Boolean(true))),
+            (
+                "a = 'x'",
+                Err(Error::TypeMismatch(
+                    "= is not defined for INTEGER and TEXT".to_string(),
+                )),
+            ),
+            // NULL propagation
+            ("n + 1", Ok(Null)),
+            ("-n", Ok(Null)),
+            ("n || 'x'", Ok(Null)),
+            ("n = n", Ok(Null)),
+            ("n <> 1", Ok(Null)),
+            ("NULL = NULL", Ok(Null)),
+            ("n / 0", Ok(Null)),
+            ("NOT n = 1", Ok(Null)),
+            ("n IS NULL", Ok(Boolean(true))),
+            ("n IS NOT NULL", Ok(Boolean(false))),
+            ("a IS NULL", Ok(Boolean(false))),
+            ("NULL IS NULL", Ok(Boolean(true))),
+            // three-valued logic
+            ("TRUE AND TRUE", Ok(Boolean(true))),
+            ("TRUE AND FALSE", Ok(Boolean(false))),
+            ("NULL AND TRUE", Ok(Null)),
+            ("TRUE AND NULL", Ok(Null)),
+            ("NULL AND FALSE", Ok(Boolean(false))),
+            ("FALSE AND NULL", Ok(Boolean(false))),
+            ("NULL AND NULL", Ok(Null)),
+            ("NULL OR TRUE", Ok(Boolean(true))),
+            ("TRUE OR NULL", Ok(Boolean(true))),
+            ("NULL OR FALSE", Ok(Null)),
+            ("FALSE OR NULL", Ok(Null)),
+            ("FALSE OR FALSE", Ok(Boolean(false))),
+            ("NULL OR NULL", Ok(Null)),
+            ("NOT t", Ok(Boolean(false))),
+            ("NOT NULL", Ok(Null)),
+            ("a = 1 AND (s = 'x' OR n > 0)", Ok(Boolean(true))),
+            (
+                "a AND t",
+                Err(Error::TypeMismatch(
+                    "AND is not defined for INTEGER".to_string(),
+                )),
+            ),
+            (
+                "NOT a",
+                Err(Error::TypeMismatch(
+                    "NOT is not defined for INTEGER".to_string(),
+                )),
+            ),
+        ];
+        let schema = schema();
+        let row = row();
+        for (sql, expected) in cases {
+            let expr = Expr::from(parse_expr(sql).unwrap());
+            assert_eq!(expected, expr.eval(&row, &schema), "{}", sql);
+            // a bound expression evaluates to the same value
+            if let Ok(bound) = expr.bind(&schema) {
+                assert_eq!(expected, bound.eval(&row, &schema), "bound {}", sql);
+            }
+        }
+    }
+
+    #[test]
+    fn test_like_match() {
+        // (text, pattern, escape, expected)
+        let cases = [
+            ("", "", None, true),
+            ("", "%", None, true),
+            ("", "_", None, false),
+            ("abc", "abc", None, true),
+            ("abc", "ab", None, false),
+            ("abc", "a%", None, true),
+            ("abc", "%c", None, true),
+            ("abc", "%b%", None, true),
+            ("abc", "a_c", None, true),
+            ("abc", "a__c", None, false),
+            ("abc", "%%%", None, true),
+            ("abcbc", "a%bc", None, true),
+            ("abcbd", "a%bc", None, false),
+            ("mississippi", "m%iss%ppi", None, true),
+            ("mississippi", "m%iss%pi%x", None, false),
+            // escapes
+            ("10%", "10!%", Some('!'), true),
+            ("100", "10!%", Some('!'), false),
+            ("a_b", "a!_b", Some('!'), true),
+            ("axb", "a!_b", Some('!'), false),
+            ("a!b", "a!!b", Some('!'), true),
+            ("a%b", "a\\%b", Some('\\'), true),
+            ("a%b", "a%%b", Some('%'), true),
+            ("axb", "a%%b", Some('%'), false),
+            // multi-byte characters are single characters
+            ("日本語", "日_語", None, true),
+            ("日本語", "%語", None, true),
+            ("日本語", "日__語", None, false),
+            ("café", "caf_", None, true),
+            ("naïve", "%ï%", None, true),
+        ];
+        for (text, pattern, escape, expected) in cases {
+            let tokens = like_tokens(pattern, escape).unwrap();
+            let text: Vec<char> = text.chars().collect();
+            assert_eq!(
+                expected,
+                like_match(&text, &tokens),
+                "{:?} LIKE {:?}",
+                text,
+                pattern
+            );
+        }
+
+        // adversarial for a backtracking matcher
+        let text: Vec<char> = "a".repeat(10_000).chars().collect();
+        let tokens = like_tokens(&format!("{}%b", "%a".repeat(20)), None).unwrap();
+        assert!(!like_match(&text, &tokens));
+    }
+
+    #[test]
+    fn test_tri_bool() {
+        use TriBool::*;
+        let values = [True, False, Unknown];
+        // truth tables of SQL:2016 8.1, indexed like values
+        let and = [
+            [True, False, Unknown],
+            [False, False, False],
+            [Unknown, False, Unknown],
+        ];
+        let or = [
+            [True, True, True],
+            [True, False, Unknown],
+            [True, Unknown, Unknown],
+        ];
+        let not = [False, True, Unknown];
+        for (i, &x) in values.iter().enumerate() {
+            assert_eq!(not[i], !x);
+            for (j, &y) in values.iter().enumerate() {
+                assert_eq!(and[i][j], x.and(y), "{:?} AND {:?}", x, y);
+                assert_eq!(or[i][j], x.or(y), "{:?} OR {:?}", x, y);
+            }
+        }
+        assert_eq!([true, false, false], values.map(TriBool::matches));
+        assert_eq!([true, false, true], values.map(TriBool::passes_check));
+
+        // the evaluator agrees with the truth tables
+        let literal = |x: TriBool| match x {
+            True => "TRUE",
+            False => "FALSE",
+            Unknown => "NULL",
+        };
+        let (row, schema) = (row(), schema());
+        for (i, &x) in values.iter().enumerate() {
+            let sql = format!("NOT {}", literal(x));
+            let expr = Expr::from(parse_expr(&sql).unwrap());
+            assert_eq!(
+                not[i],
+                expr.eval_predicate(&row, &schema).unwrap(),
+                "{}",
+                sql
+            );
+            for (j, &y) in values.iter().enumerate() {
+                for (op, table) in [("AND", &and), ("OR", &or)] {
+                    let sql = format!("{} {} {}", literal(x), op, literal(y));
+                    let expr = Expr::from(parse_expr(&sql).unwrap());
+                    assert_eq!(
+                        table[i][j],
+                        expr.eval_predicate(&row, &schema).unwrap(),
+                        "{}",
+                        sql
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_eval_predicate() {
+        let (row, schema) = (row(), schema());
+        // (predicate, passes WHERE, passes CHECK)
+        let cases = [
+            ("a = 1", true, true),
+            ("a = 2", false, false),
+            ("n = 1", false, true),
+            ("n <> 1", false, true),
+            ("NOT n = 1", false, true),
+            ("n = 1 OR a = 1", true, true),
+            ("n = 1 AND a = 2", false, false),
+            ("n IS NULL", true, true),
+            ("n IS NOT NULL", false, false),
+            ("a IN (2, n)", false, true),
+        ];
+        for (sql, where_result, check_result) in cases {
+            let result = Expr::from(parse_expr(sql).unwrap())
+                .eval_predicate(&row, &schema)
+                .unwrap();
+            assert_eq!(
+                (where_result, check_result),
+                (result.matches(), result.passes_check()),
+                "{}",
+                sql
+            );
+        }
+        let error = Expr::from(parse_expr("a + 1").unwrap()).eval_predicate(&row, &schema);
+        assert_eq!(
+            Err(Error::TypeMismatch(
+                "predicate must be BOOLEAN but is INTEGER".to_string()
+            )),
+            error
+        );
+    }
+
+    #[test]
+    fn test_eval_borrows() {
+        let schema = schema();
+        let row = row();
+        // columns and literals are not cloned
+        let expr = Expr::from(parse_expr("s").unwrap()).bind(&schema).unwrap();
+        assert!(matches!(
+            expr.eval_cow(&row, &schema),
+            Ok(Cow::Borrowed(Value::Text(_)))
+        ));
+        let expr = Expr::from(parse_expr("COALESCE(n, 'y')").unwrap());
+        assert!(matches!(
+            expr.eval_cow(&row, &schema),
+            Ok(Cow::Borrowed(Value::Text(_)))
+        ));
+        let expr = Expr::from(parse_expr("s = 'x'").unwrap());
+        assert_eq!(Ok(Value::Boolean(true)), expr.eval(&row, &schema));
+    }
+
+    #[test]
+    fn test_bind() {
+        let schema = schema();
+        let expr = Expr::from(parse_expr("b > 1 AND s IS NOT NULL").unwrap());
+        let bound = expr.bind(&schema).unwrap();
+        assert_eq!(
+            Expr::Binary {
+                left: Box::new(Expr::Binary {
+                    left: Box::new(Expr::ColumnIndex(1)),
+                    op: BinaryOp::Gt,
+                    right: Box::new(Expr::Literal(Value::Integer(1))),
+                }),
+                op: BinaryOp::And,
+                right: Box::new(Expr::IsNull {
+                    expr: Box::new(Expr::ColumnIndex(2)),
+                    negated: true,
+                }),
+            },
+            bound
+        );
+        let expr = Expr::from(parse_expr("a + missing").unwrap());
+        assert_eq!(
+            Err(Error::UnknownColumn("missing".to_string())),
+            expr.bind(&schema)
+        );
+        assert_eq!(
+            Err(Error::ColumnOutOfRange(5)),
+            Expr::ColumnIndex(5).eval(&row(), &schema)
+        );
+    }
+}
@@ -1,2 +1,5 @@
 pub mod disk;
 pub mod buffer;
+pub mod sql;
+pub mod value;
+pub mod expr;
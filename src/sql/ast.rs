@@ -0,0 +1,537 @@
+use std::fmt;
+
+use super::lexer::Keyword;
+pub use crate::value::DataType;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    CreateTable(CreateTable),
+    DropTable(DropTable),
+    CreateIndex(CreateIndex),
+    Insert(Insert),
+    Select(Select),
+    Update(Update),
+    Delete(Delete),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateTable {
+    pub name: String,
+    pub columns: Vec<ColumnDef>,
+    // table constraint PRIMARY KEY (a, b)
+    pub primary_key: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnDef {
+    pub name: String,
+    pub data_type: DataType,
+    pub not_null: bool,
+    pub default: Option<Expr>,
+    // column constraint PRIMARY KEY
+    pub primary_key: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropTable {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateIndex {
+    pub name: String,
+    pub table: String,
+    pub columns: Vec<String>,
+    pub unique: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Insert {
+    pub table: String,
+    // empty when the column list is omitted
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Expr>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Select {
+    pub projection: Vec<SelectItem>,
+    pub from: Option<String>,
+    pub where_clause: Option<Expr>,
+    pub order_by: Vec<OrderBy>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectItem {
+    Wildcard,
+    Expr { expr: Expr, alias: Option<String> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderBy {
+    pub expr: Expr,
+    pub asc: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Update {
+    pub table: String,
+    pub assignments: Vec<(String, Expr)>,
+    pub where_clause: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delete {
+    pub table: String,
+    pub where_clause: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Literal),
+    Column(String),
+    Unary {
+        op: UnaryOp,
+        expr: Box<Expr>,
+    },
+    Binary {
+        left: Box<Expr>,
+        op: BinaryOp,
+        right: Box<Expr>,
+    },
+    IsNull {
+        expr: Box<Expr>,
+        negated: bool,
+    },
+    // expr [NOT] IN (list, ...)
+    InList {
+        expr: Box<Expr>,
+        list: Vec<Expr>,
+        negated: bool,
+    },
+    // expr [NOT] BETWEEN low AND high, the range is inclusive
+    Between {
+        expr: Box<Expr>,
+        low: Box<Expr>,
+        high: Box<Expr>,
+        negated: bool,
+    },
+    // expr [NOT] LIKE pattern [ESCAPE escape]
+    Like {
+        expr: Box<Expr>,
+        pattern: Box<Expr>,
+        escape: Option<Box<Expr>>,
+        negated: bool,
+    },
+    // name(arg, ...), the name is resolved when the expression is bound
+    Function {
+        name: String,
+        args: Vec<Expr>,
+    },
+    // CAST(expr AS data_type)
+    Cast {
+        expr: Box<Expr>,
+        data_type: DataType,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum UnaryOp {
+    Not,
+    Minus,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    Concat,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+
+// Binding power of the operators, higher binds tighter
+pub(crate) mod precedence {
+    pub const OR: u8 = 1;
+    pub const AND: u8 = 2;
+    pub const NOT: u8 = 3;
+    pub const COMPARISON: u8 = 4;
+    pub const CONCAT: u8 = 5;
+    pub const ADDITIVE: u8 = 6;
+    pub const MULTIPLICATIVE: u8 = 7;
+    pub const UNARY_MINUS: u8 = 8;
+    pub const PRIMARY: u8 = 9;
+}
+
+impl BinaryOp {
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Or => precedence::OR,
+            BinaryOp::And => precedence::AND,
+            BinaryOp::Eq
+            | BinaryOp::NotEq
+            | BinaryOp::Lt
+            | BinaryOp::LtEq
+            | BinaryOp::Gt
+            | BinaryOp::GtEq => precedence::COMPARISON,
+            BinaryOp::Concat => precedence::CONCAT,
+            BinaryOp::Add | BinaryOp::Sub => precedence::ADDITIVE,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => precedence::MULTIPLICATIVE,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BinaryOp::Or => "OR",
+            BinaryOp::And => "AND",
+            BinaryOp::Eq => "=",
+            BinaryOp::NotEq => "<>",
+            BinaryOp::Lt => "<",
+            BinaryOp::LtEq => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::GtEq => ">=",
+            BinaryOp::Concat => "||",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Mod => "%",
+        }
+    }
+}
+
+impl Expr {
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Expr::Literal(_) | Expr::Column(_) | Expr::Function { .. } | Expr::Cast { .. } => {
+                precedence::PRIMARY
+            }
+            Expr::Unary {
+                op: UnaryOp::Not, ..
+            } => precedence::NOT,
+            Expr::Unary {
+                op: UnaryOp::Minus, ..
+            } => precedence::UNARY_MINUS,
+            Expr::Binary { op, .. } => op.precedence(),
+            Expr::IsNull { .. }
+            | Expr::InList { .. }
+            | Expr::Between { .. }
+            | Expr::Like { .. } => precedence::COMPARISON,
+        }
+    }
+
+    // write the expression, parenthesized when it binds looser than min_precedence
+    fn fmt_operand(&self, f: &mut fmt::Formatter, min_precedence: u8) -> fmt::Result {
+        if self.precedence() < min_precedence {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+// Identifiers are written bare when they re-lex as the same identifier, quoted otherwise
+pub struct Ident<'a>(pub &'a str);
+
+impl fmt::Display for Ident<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut chars = self.0.chars();
+        let is_bare = matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+            && chars.all(|c| c.is_alphanumeric() || c == '_')
+            && Keyword::lookup(self.0).is_none();
+        if is_bare {
+            write!(f, "{}", self.0)
+        } else {
+            write!(f, "\"{}\"", self.0.replace('"', "\"\""))
+        }
+    }
+}
+
+// comma separated list
+struct List<'a, T>(&'a [T]);
+
+impl<T: fmt::Display> fmt::Display for List<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, item) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", item)?;
+        }
+        Ok(())
+    }
+}
+
+struct IdentList<'a>(&'a [String]);
+
+impl fmt::Display for IdentList<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let idents: Vec<Ident> = self.0.iter().map(|name| Ident(name)).collect();
+        write!(f, "{}", List(&idents))
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Statement::CreateTable(create_table) => write!(f, "{}", create_table),
+            Statement::DropTable(drop_table) => write!(f, "{}", drop_table),
+            Statement::CreateIndex(create_index) => write!(f, "{}", create_index),
+            Statement::Insert(insert) => write!(f, "{}", insert),
+            Statement::Select(select) => write!(f, "{}", select),
+            Statement::Update(update) => write!(f, "{}", update),
+            Statement::Delete(delete) => write!(f, "{}", delete),
+        }
+    }
+}
+
+impl fmt::Display for CreateTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "CREATE TABLE {} ({}",
+            Ident(&self.name),
+            List(&self.columns)
+        )?;
+        if let Some(primary_key) = &self.primary_key {
+            write!(f, ", PRIMARY KEY ({})", IdentList(primary_key))?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl fmt::Display for ColumnDef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", Ident(&self.name), self.data_type)?;
+        if self.primary_key {
+            write!(f, " PRIMARY KEY")?;
+        }
+        if self.not_null {
+            write!(f, " NOT NULL")?;
+        }
+        if let Some(default) = &self.default {
+            write!(f, " DEFAULT ")?;
+            default.fmt_operand(f, precedence::UNARY_MINUS)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for DropTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DROP TABLE {}", Ident(&self.name))
+    }
+}
+
+impl fmt::Display for CreateIndex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unique = if self.unique { "UNIQUE " } else { "" };
+        write!(
+            f,
+            "CREATE {}INDEX {} ON {} ({})",
+            unique,
+            Ident(&self.name),
+            Ident(&self.table),
+            IdentList(&self.columns)
+        )
+    }
+}
+
+impl fmt::Display for Insert {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "INSERT INTO {}", Ident(&self.table))?;
+        if !self.columns.is_empty() {
+            write!(f, " ({})", IdentList(&self.columns))?;
+        }
+        write!(f, " VALUES ")?;
+        for (i, row) in self.rows.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "({})", List(row))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Select {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SELECT {}", List(&self.projection))?;
+        if let Some(from) = &self.from {
+            write!(f, " FROM {}", Ident(from))?;
+        }
+        if let Some(where_clause) = &self.where_clause {
+            write!(f, " WHERE {}", where_clause)?;
+        }
+        if !self.order_by.is_empty() {
+            write!(f, " ORDER BY {}", List(&self.order_by))?;
+        }
+        if let Some(limit) = self.limit {
+            write!(f, " LIMIT {}", limit)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " OFFSET {}", offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SelectItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SelectItem::Wildcard => write!(f, "*"),
+            SelectItem::Expr { expr, alias } => {
+                write!(f, "{}", expr)?;
+                if let Some(alias) = alias {
+                    write!(f, " AS {}", Ident(alias))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for OrderBy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let direction = if self.asc { "ASC" } else { "DESC" };
+        write!(f, "{} {}", self.expr, direction)
+    }
+}
+
+impl fmt::Display for Update {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "UPDATE {} SET ", Ident(&self.table))?;
+        for (i, (column, expr)) in self.assignments.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} = {}", Ident(column), expr)?;
+        }
+        if let Some(where_clause) = &self.where_clause {
+            write!(f, " WHERE {}", where_clause)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Delete {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DELETE FROM {}", Ident(&self.table))?;
+        if let Some(where_clause) = &self.where_clause {
+            write!(f, " WHERE {}", where_clause)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Literal(literal) => write!(f, "{}", literal),
+            Expr::Column(name) => write!(f, "{}", Ident(name)),
+            Expr::Unary {
+                op: UnaryOp::Not,
+                expr,
+            } => {
+                write!(f, "NOT ")?;
+                expr.fmt_operand(f, precedence::NOT)
+            }
+            Expr::Unary {
+                op: UnaryOp::Minus,
+                expr,
+            } => {
+                write!(f, "-")?;
+                // NOTE: "--" would start a comment, so a nested minus is always parenthesized
+                expr.fmt_operand(f, precedence::UNARY_MINUS + 1)
+            }
+            Expr::Binary { left, op, right } => {
+                // operators are left associative, so the right operand of the same precedence needs parentheses
+                left.fmt_operand(f, op.precedence())?;
+                write!(f, " {} ", op.as_str())?;
+                right.fmt_operand(f, op.precedence() + 1)
+            }
+            Expr::IsNull { expr, negated } => {
+                expr.fmt_operand(f, precedence::COMPARISON + 1)?;
+                if *negated {
+                    write!(f, " IS NOT NULL")
+                } else {
+                    write!(f, " IS NULL")
+                }
+            }
+            Expr::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                expr.fmt_operand(f, precedence::COMPARISON + 1)?;
+                let not = if *negated { " NOT" } else { "" };
+                write!(f, "{} IN ({})", not, List(list))
+            }
+            Expr::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => {
+                expr.fmt_operand(f, precedence::COMPARISON + 1)?;
+                let not = if *negated { " NOT" } else { "" };
+                write!(f, "{} BETWEEN ", not)?;
+                low.fmt_operand(f, precedence::COMPARISON + 1)?;
+                write!(f, " AND ")?;
+                high.fmt_operand(f, precedence::COMPARISON + 1)
+            }
+            Expr::Like {
+                expr,
+                pattern,
+                escape,
+                negated,
+            } => {
+                expr.fmt_operand(f, precedence::COMPARISON + 1)?;
+                let not = if *negated { " NOT" } else { "" };
+                write!(f, "{} LIKE ", not)?;
+                pattern.fmt_operand(f, precedence::COMPARISON + 1)?;
+                if let Some(escape) = escape {
+                    write!(f, " ESCAPE ")?;
+                    escape.fmt_operand(f, precedence::COMPARISON + 1)?;
+                }
+                Ok(())
+            }
+            Expr::Function { name, args } => write!(f, "{}({})", Ident(name), List(args)),
+            Expr::Cast { expr, data_type } => write!(f, "CAST({} AS {})", expr, data_type),
+        }
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Literal::Null => write!(f, "NULL"),
+            Literal::Boolean(true) => write!(f, "TRUE"),
+            Literal::Boolean(false) => write!(f, "FALSE"),
+            Literal::Integer(integer) => write!(f, "{}", integer),
+            // NOTE: {:?} keeps the fraction of integral values (1.0), so it is lexed as a float again
+            Literal::Float(float) => write!(f, "{:?}", float),
+            Literal::String(string) => write!(f, "'{}'", string.replace('\'', "''")),
+        }
+    }
+}
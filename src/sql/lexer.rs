@@ -0,0 +1,396 @@
+use std::fmt;
+
+use super::Error;
+
+macro_rules! keywords {
+    ($($variant:ident => $text:literal,)*) => {
+        // Reserved words. They are matched case-insensitively.
+        #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+        pub enum Keyword {
+            $($variant,)*
+        }
+
+        impl Keyword {
+            pub fn lookup(word: &str) -> Option<Keyword> {
+                $(
+                    if word.eq_ignore_ascii_case($text) {
+                        return Some(Keyword::$variant);
+                    }
+                )*
+                None
+            }
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(Keyword::$variant => $text,)*
+                }
+            }
+        }
+    };
+}
+
+keywords! {
+    And => "AND",
+    As => "AS",
+    Asc => "ASC",
+    Between => "BETWEEN",
+    By => "BY",
+    Cast => "CAST",
+    Create => "CREATE",
+    Default => "DEFAULT",
+    Delete => "DELETE",
+    Desc => "DESC",
+    Drop => "DROP",
+    Escape => "ESCAPE",
+    False => "FALSE",
+    From => "FROM",
+    In => "IN",
+    Index => "INDEX",
+    Insert => "INSERT",
+    Into => "INTO",
+    Is => "IS",
+    Key => "KEY",
+    Like => "LIKE",
+    Limit => "LIMIT",
+    Not => "NOT",
+    Null => "NULL",
+    Offset => "OFFSET",
+    On => "ON",
+    Or => "OR",
+    Order => "ORDER",
+    Primary => "PRIMARY",
+    Select => "SELECT",
+    Set => "SET",
+    Table => "TABLE",
+    True => "TRUE",
+    Unique => "UNIQUE",
+    Update => "UPDATE",
+    Values => "VALUES",
+    Where => "WHERE",
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Keyword(Keyword),
+    // unquoted identifiers keep their spelling, quoted ones ("...") may contain anything
+    Ident(String),
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Comma,
+    LParen,
+    RParen,
+    Semicolon,
+    Dot,
+    Star,
+    Plus,
+    Minus,
+    Slash,
+    Percent,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    Concat,
+    Eof,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Keyword(keyword) => write!(f, "{}", keyword.as_str()),
+            Token::Ident(ident) => write!(f, "identifier \"{}\"", ident),
+            Token::String(string) => write!(f, "string '{}'", string),
+            Token::Integer(integer) => write!(f, "number {}", integer),
+            Token::Float(float) => write!(f, "number {:?}", float),
+            Token::Comma => write!(f, "','"),
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::Semicolon => write!(f, "';'"),
+            Token::Dot => write!(f, "'.'"),
+            Token::Star => write!(f, "'*'"),
+            Token::Plus => write!(f, "'+'"),
+            Token::Minus => write!(f, "'-'"),
+            Token::Slash => write!(f, "'/'"),
+            Token::Percent => write!(f, "'%'"),
+            Token::Eq => write!(f, "'='"),
+            Token::NotEq => write!(f, "'<>'"),
+            Token::Lt => write!(f, "'<'"),
+            Token::LtEq => write!(f, "'<='"),
+            Token::Gt => write!(f, "'>'"),
+            Token::GtEq => write!(f, "'>='"),
+            Token::Concat => write!(f, "'||'"),
+            Token::Eof => write!(f, "end of input"),
+        }
+    }
+}
+
+// Token with the byte offset where it starts
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned {
+    pub token: Token,
+    pub offset: usize,
+}
+
+// Split the SQL text into tokens. The last token is always Token::Eof.
+// Whitespace and comments (-- line and /* block */) are skipped.
+pub fn tokenize(sql: &str) -> Result<Vec<Spanned>, Error> {
+    let mut lexer = Lexer { sql, pos: 0 };
+    let mut tokens = vec![];
+    loop {
+        lexer.skip_whitespace_and_comments()?;
+        let offset = lexer.pos;
+        let token = lexer.next_token()?;
+        let is_eof = token == Token::Eof;
+        tokens.push(Spanned { token, offset });
+        if is_eof {
+            return Ok(tokens);
+        }
+    }
+}
+
+struct Lexer<'a> {
+    sql: &'a str,
+    // current byte offset
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn peek(&self) -> Option<char> {
+        self.sql[self.pos..].chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.sql[self.pos..].chars().nth(1)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn error(&self, offset: usize, message: impl Into<String>) -> Error {
+        Error::new(self.sql, offset, message)
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> Result<(), Error> {
+        loop {
+            match (self.peek(), self.peek_second()) {
+                (Some(c), _) if c.is_whitespace() => {
+                    self.bump();
+                }
+                (Some('-'), Some('-')) => {
+                    while let Some(c) = self.bump() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                }
+                (Some('/'), Some('*')) => {
+                    let start = self.pos;
+                    self.pos += 2;
+                    match self.sql[self.pos..].find("*/") {
+                        Some(end) => self.pos += end + 2,
+                        None => return Err(self.error(start, "unterminated block comment")),
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Token, Error> {
+        let start = self.pos;
+        let c = match self.bump() {
+            Some(c) => c,
+            None => return Ok(Token::Eof),
+        };
+        let token = match c {
+            ',' => Token::Comma,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ';' => Token::Semicolon,
+            '.' => Token::Dot,
+            '*' => Token::Star,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '/' => Token::Slash,
+            '%' => Token::Percent,
+            '=' => Token::Eq,
+            '<' => match self.peek() {
+                Some('=') => {
+                    self.bump();
+                    Token::LtEq
+                }
+                Some('>') => {
+                    self.bump();
+                    Token::NotEq
+                }
+                _ => Token::Lt,
+            },
+            '>' => match self.peek() {
+                Some('=') => {
+                    self.bump();
+                    Token::GtEq
+                }
+                _ => Token::Gt,
+            },
+            '!' if self.peek() == Some('=') => {
+                self.bump();
+                Token::NotEq
+            }
+            '|' if self.peek() == Some('|') => {
+                self.bump();
+                Token::Concat
+            }
+            '\'' => Token::String(self.quoted(start, '\'')?),
+            '"' => Token::Ident(self.quoted(start, '"')?),
+            c if c.is_ascii_digit() => self.number(start)?,
+            c if c.is_alphabetic() || c == '_' => {
+                while matches!(self.peek(), Some(c) if c.is_alphanumeric() || c == '_') {
+                    self.bump();
+                }
+                let word = &self.sql[start..self.pos];
+                match Keyword::lookup(word) {
+                    Some(keyword) => Token::Keyword(keyword),
+                    None => Token::Ident(word.to_string()),
+                }
+            }
+            c => return Err(self.error(start, format!("unexpected character '{}'", c))),
+        };
+        Ok(token)
+    }
+
+    // body of a quoted string or identifier, a doubled quote stands for the quote itself
+    fn quoted(&mut self, start: usize, quote: char) -> Result<String, Error> {
+        let mut text = String::new();
+        loop {
+            match self.bump() {
+                Some(c) if c == quote => {
+                    if self.peek() == Some(quote) {
+                        self.bump();
+                        text.push(quote);
+                    } else {
+                        return Ok(text);
+                    }
+                }
+                Some(c) => text.push(c),
+                None => {
+                    let what = if quote == '\'' {
+                        "string"
+                    } else {
+                        "quoted identifier"
+                    };
+                    return Err(self.error(start, format!("unterminated {}", what)));
+                }
+            }
+        }
+    }
+
+    // digits [. digits] [e [+-] digits]
+    fn number(&mut self, start: usize) -> Result<Token, Error> {
+        let mut is_float = false;
+        self.digits();
+        if self.peek() == Some('.') && matches!(self.peek_second(), Some(c) if c.is_ascii_digit()) {
+            is_float = true;
+            self.bump();
+            self.digits();
+        }
+        if matches!(self.peek(), Some('e') | Some('E')) {
+            let mark = self.pos;
+            self.bump();
+            if matches!(self.peek(), Some('+') | Some('-')) {
+                self.bump();
+            }
+            if matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+                is_float = true;
+                self.digits();
+            } else {
+                // not an exponent, e.g. "1else"
+                self.pos = mark;
+            }
+        }
+        let text = &self.sql[start..self.pos];
+        if is_float {
+            match text.parse::<f64>() {
+                Ok(float) if float.is_finite() => Ok(Token::Float(float)),
+                _ => Err(self.error(start, format!("number {} is out of range", text))),
+            }
+        } else {
+            text.parse::<i64>()
+                .map(Token::Integer)
+                .map_err(|_| self.error(start, format!("integer {} is out of range", text)))
+        }
+    }
+
+    fn digits(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+            self.bump();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(sql: &str) -> Vec<Token> {
+        tokenize(sql)
+            .unwrap()
+            .into_iter()
+            .map(|spanned| spanned.token)
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            vec![
+                Token::Keyword(Keyword::Select),
+                Token::Ident("a".to_string()),
+                Token::Comma,
+                Token::Ident("Select".to_string()),
+                Token::Comma,
+                Token::String("it's".to_string()),
+                Token::Comma,
+                Token::Integer(42),
+                Token::Comma,
+                Token::Float(1.5e3),
+                Token::Keyword(Keyword::From),
+                Token::Ident("t".to_string()),
+                Token::Keyword(Keyword::Where),
+                Token::Ident("a".to_string()),
+                Token::LtEq,
+                Token::Integer(1),
+                Token::Keyword(Keyword::Or),
+                Token::Ident("b".to_string()),
+                Token::NotEq,
+                Token::Integer(2),
+                Token::Semicolon,
+                Token::Eof,
+            ],
+            tokens("select a, \"Select\", 'it''s', 42, 1.5e3 -- comment\nFROM t /* block\ncomment */ WHERE a<=1 or b != 2;")
+        );
+    }
+
+    #[test]
+    fn test_tokenize_error() {
+        let error = tokenize("SELECT 'abc").unwrap_err();
+        assert_eq!(7, error.offset);
+        assert_eq!("unterminated string", error.message);
+        let error = tokenize("SELECT 1\n  FROM t WHERE a ? 1").unwrap_err();
+        assert_eq!((2, 18), (error.line, error.column));
+        assert_eq!(
+            "unexpected character '?' at line 2, column 18",
+            error.to_string()
+        );
+        let error = tokenize("SELECT 99999999999999999999").unwrap_err();
+        assert_eq!(
+            "integer 99999999999999999999 is out of range",
+            error.message
+        );
+    }
+}
@@ -0,0 +1,32 @@
+// SQL front end: a hand-written lexer and a recursive-descent parser producing an AST.
+pub mod ast;
+pub mod lexer;
+pub mod parser;
+
+pub use parser::{parse, parse_expr, parse_statement};
+
+#[derive(Debug, Clone, Eq, PartialEq, thiserror::Error)]
+#[error("{message} at line {line}, column {column}")]
+pub struct Error {
+    // byte offset in the SQL text
+    pub offset: usize,
+    // 1-based line and column (in characters) of the offset
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Error {
+    pub fn new(sql: &str, offset: usize, message: impl Into<String>) -> Self {
+        let before = &sql[..offset];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let column = before[line_start..].chars().count() + 1;
+        Self {
+            offset,
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+}
@@ -0,0 +1,830 @@
+use super::ast::*;
+use super::lexer::{tokenize, Keyword, Spanned, Token};
+use super::Error;
+
+// Parse a sequence of statements separated by semicolons
+pub fn parse(sql: &str) -> Result<Vec<Statement>, Error> {
+    let mut parser = Parser::new(sql)?;
+    let mut statements = vec![];
+    loop {
+        while parser.consume(&Token::Semicolon) {}
+        if parser.peek() == &Token::Eof {
+            return Ok(statements);
+        }
+        statements.push(parser.parse_statement()?);
+        if parser.peek() != &Token::Eof {
+            parser.expect(&Token::Semicolon, "';' after statement")?;
+        }
+    }
+}
+
+// Parse exactly one statement, optionally terminated by a semicolon
+pub fn parse_statement(sql: &str) -> Result<Statement, Error> {
+    let mut parser = Parser::new(sql)?;
+    let statement = parser.parse_statement()?;
+    parser.consume(&Token::Semicolon);
+    parser.expect(&Token::Eof, "end of statement")?;
+    Ok(statement)
+}
+
+// Parse a standalone expression, e.g. a CHECK constraint or a REPL calculation
+pub fn parse_expr(sql: &str) -> Result<Expr, Error> {
+    let mut parser = Parser::new(sql)?;
+    let expr = parser.parse_expr()?;
+    parser.expect(&Token::Eof, "end of expression")?;
+    Ok(expr)
+}
+
+// Deepest nesting of expressions (parentheses, unary operators,...) the parser accepts.
+// It recurses once per level, so unbounded nesting would overflow the stack.
+const MAX_DEPTH: usize = 256;
+
+// Recursive-descent parser over the token stream
+struct Parser<'a> {
+    sql: &'a str,
+    tokens: Vec<Spanned>,
+    pos: usize,
+    // number of parse_expr_bp calls in progress
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(sql: &'a str) -> Result<Self, Error> {
+        let tokens = tokenize(sql)?;
+        Ok(Self {
+            sql,
+            tokens,
+            pos: 0,
+            depth: 0,
+        })
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].token
+    }
+
+    fn peek_second(&self) -> &Token {
+        let pos = (self.pos + 1).min(self.tokens.len() - 1);
+        &self.tokens[pos].token
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens[self.pos].offset
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.pos].token.clone();
+        // NOTE: never move past Eof
+        if token != Token::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn consume(&mut self, token: &Token) -> bool {
+        if self.peek() == token {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn consume_keyword(&mut self, keyword: Keyword) -> bool {
+        self.consume(&Token::Keyword(keyword))
+    }
+
+    fn error(&self, message: impl Into<String>) -> Error {
+        Error::new(self.sql, self.offset(), message)
+    }
+
+    // error for an unexpected current token, e.g. "expected ')' after column list but found FROM"
+    fn expected(&self, what: &str) -> Error {
+        self.error(format!("expected {} but found {}", what, self.peek()))
+    }
+
+    fn expect(&mut self, token: &Token, what: &str) -> Result<(), Error> {
+        if self.consume(token) {
+            Ok(())
+        } else {
+            Err(self.expected(what))
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: Keyword, what: &str) -> Result<(), Error> {
+        self.expect(&Token::Keyword(keyword), what)
+    }
+
+    fn parse_ident(&mut self, what: &str) -> Result<String, Error> {
+        match self.peek() {
+            Token::Ident(ident) => {
+                let ident = ident.clone();
+                self.advance();
+                Ok(ident)
+            }
+            _ => Err(self.expected(what)),
+        }
+    }
+
+    // ( ident, ... )
+    fn parse_ident_list(&mut self, what: &str, after: &str) -> Result<Vec<String>, Error> {
+        self.expect(&Token::LParen, &format!("'(' before {}", after))?;
+        let mut idents = vec![self.parse_ident(what)?];
+        while self.consume(&Token::Comma) {
+            idents.push(self.parse_ident(what)?);
+        }
+        self.expect(&Token::RParen, &format!("')' after {}", after))?;
+        Ok(idents)
+    }
+
+    fn parse_statement(&mut self) -> Result<Statement, Error> {
+        match self.peek() {
+            Token::Keyword(Keyword::Create) => {
+                self.advance();
+                match self.peek() {
+                    Token::Keyword(Keyword::Table) => {
+                        self.parse_create_table().map(Statement::CreateTable)
+                    }
+                    Token::Keyword(Keyword::Index) | Token::Keyword(Keyword::Unique) => {
+                        self.parse_create_index().map(Statement::CreateIndex)
+                    }
+                    _ => Err(self.expected("TABLE or INDEX after CREATE")),
+                }
+            }
+            Token::Keyword(Keyword::Drop) => {
+                self.advance();
+                self.expect_keyword(Keyword::Table, "TABLE after DROP")?;
+                let name = self.parse_ident("table name")?;
+                Ok(Statement::DropTable(DropTable { name }))
+            }
+            Token::Keyword(Keyword::Insert) => self.parse_insert().map(Statement::Insert),
+            Token::Keyword(Keyword::Select) => self.parse_select().map(Statement::Select),
+            Token::Keyword(Keyword::Update) => self.parse_update().map(Statement::Update),
+            Token::Keyword(Keyword::Delete) => self.parse_delete().map(Statement::Delete),
+            _ => Err(self.expected("a statement")),
+        }
+    }
+
+    // CREATE TABLE name ( column_def, ... [, PRIMARY KEY ( column, ... )] )
+    fn parse_create_table(&mut self) -> Result<CreateTable, Error> {
+        self.expect_keyword(Keyword::Table, "TABLE")?;
+        let name = self.parse_ident("table name")?;
+        self.expect(&Token::LParen, "'(' before column list")?;
+        let mut columns = vec![];
+        let mut primary_key = None;
+        loop {
+            if self.peek() == &Token::Keyword(Keyword::Primary) {
+                if primary_key.is_some() {
+                    return Err(self.error("multiple primary keys for table"));
+                }
+                self.advance();
+                self.expect_keyword(Keyword::Key, "KEY after PRIMARY")?;
+                primary_key = Some(self.parse_ident_list("column name", "primary key columns")?);
+            } else {
+                columns.push(self.parse_column_def()?);
+            }
+            if !self.consume(&Token::Comma) {
+                break;
+            }
+        }
+        self.expect(&Token::RParen, "')' after column list")?;
+        Ok(CreateTable {
+            name,
+            columns,
+            primary_key,
+        })
+    }
+
+    fn parse_column_def(&mut self) -> Result<ColumnDef, Error> {
+        let name = self.parse_ident("column name")?;
+        let data_type = self.parse_data_type()?;
+        let mut column = ColumnDef {
+            name,
+            data_type,
+            not_null: false,
+            default: None,
+            primary_key: false,
+        };
+        loop {
+            match self.peek() {
+                Token::Keyword(Keyword::Primary) => {
+                    self.advance();
+                    self.expect_keyword(Keyword::Key, "KEY after PRIMARY")?;
+                    column.primary_key = true;
+                }
+                Token::Keyword(Keyword::Not) => {
+                    self.advance();
+                    self.expect_keyword(Keyword::Null, "NULL after NOT")?;
+                    column.not_null = true;
+                }
+                Token::Keyword(Keyword::Default) => {
+                    self.advance();
+                    // NOTE: a full expression would swallow the following constraints
+                    column.default = Some(self.parse_expr_bp(precedence::UNARY_MINUS)?);
+                }
+                _ => return Ok(column),
+            }
+        }
+    }
+
+    fn parse_data_type(&mut self) -> Result<DataType, Error> {
+        let name = match self.peek() {
+            Token::Ident(name) => name.to_ascii_uppercase(),
+            _ => return Err(self.expected("data type")),
+        };
+        let data_type = match name.as_str() {
+            "INTEGER" | "INT" | "BIGINT" => DataType::Integer,
+            "FLOAT" | "REAL" | "DOUBLE" => DataType::Float,
+            "TEXT" | "VARCHAR" => DataType::Text,
+            "BOOLEAN" | "BOOL" => DataType::Boolean,
+            _ => return Err(self.error(format!("unknown data type {}", name))),
+        };
+        self.advance();
+        // NOTE: the length of VARCHAR(n) is accepted but not enforced
+        if name == "VARCHAR" && self.consume(&Token::LParen) {
+            match self.advance() {
+                Token::Integer(_) => {}
+                _ => return Err(self.expected("length of VARCHAR")),
+            }
+            self.expect(&Token::RParen, "')' after length of VARCHAR")?;
+        }
+        Ok(data_type)
+    }
+
+    // CREATE [UNIQUE] INDEX name ON table ( column, ... )
+    fn parse_create_index(&mut self) -> Result<CreateIndex, Error> {
+        let unique = self.consume_keyword(Keyword::Unique);
+        self.expect_keyword(Keyword::Index, "INDEX")?;
+        let name = self.parse_ident("index name")?;
+        self.expect_keyword(Keyword::On, "ON after index name")?;
+        let table = self.parse_ident("table name")?;
+        let columns = self.parse_ident_list("column name", "index columns")?;
+        Ok(CreateIndex {
+            name,
+            table,
+            columns,
+            unique,
+        })
+    }
+
+    // INSERT INTO table [( column, ... )] VALUES ( expr, ... ), ...
+    fn parse_insert(&mut self) -> Result<Insert, Error> {
+        self.expect_keyword(Keyword::Insert, "INSERT")?;
+        self.expect_keyword(Keyword::Into, "INTO after INSERT")?;
+        let table = self.parse_ident("table name")?;
+        let columns = if self.peek() == &Token::LParen {
+            self.parse_ident_list("column name", "column list")?
+        } else {
+            vec![]
+        };
+        self.expect_keyword(Keyword::Values, "VALUES")?;
+        let mut rows = vec![];
+        loop {
+            self.expect(&Token::LParen, "'(' before row values")?;
+            let mut row = vec![self.parse_expr()?];
+            while self.consume(&Token::Comma) {
+                row.push(self.parse_expr()?);
+            }
+            self.expect(&Token::RParen, "')' after row values")?;
+            rows.push(row);
+            if !self.consume(&Token::Comma) {
+                break;
+            }
+        }
+        Ok(Insert {
+            table,
+            columns,
+            rows,
+        })
+    }
+
+    // SELECT item, ... [FROM table] [WHERE expr] [ORDER BY expr [ASC|DESC], ...] [LIMIT n] [OFFSET n]
+    fn parse_select(&mut self) -> Result<Select, Error> {
+        self.expect_keyword(Keyword::Select, "SELECT")?;
+        let mut projection = vec![self.parse_select_item()?];
+        while self.consume(&Token::Comma) {
+            projection.push(self.parse_select_item()?);
+        }
+        let from = if self.consume_keyword(Keyword::From) {
+            Some(self.parse_ident("table name")?)
+        } else {
+            None
+        };
+        let where_clause = self.parse_where()?;
+        let mut order_by = vec![];
+        if self.consume_keyword(Keyword::Order) {
+            self.expect_keyword(Keyword::By, "BY after ORDER")?;
+            loop {
+                let expr = self.parse_expr()?;
+                let asc = !self.consume_keyword(Keyword::Desc);
+                if asc {
+                    self.consume_keyword(Keyword::Asc);
+                }
+                order_by.push(OrderBy { expr, asc });
+                if !self.consume(&Token::Comma) {
+                    break;
+                }
+            }
+        }
+        let limit = if self.consume_keyword(Keyword::Limit) {
+            Some(self.parse_count("LIMIT")?)
+        } else {
+            None
+        };
+        let offset = if self.consume_keyword(Keyword::Offset) {
+            Some(self.parse_count("OFFSET")?)
+        } else {
+            None
+        };
+        Ok(Select {
+            projection,
+            from,
+            where_clause,
+            order_by,
+            limit,
+            offset,
+        })
+    }
+
+    fn parse_select_item(&mut self) -> Result<SelectItem, Error> {
+        if self.consume(&Token::Star) {
+            return Ok(SelectItem::Wildcard);
+        }
+        let expr = self.parse_expr()?;
+        let alias = if self.consume_keyword(Keyword::As) {
+            Some(self.parse_ident("alias after AS")?)
+        } else {
+            None
+        };
+        Ok(SelectItem::Expr { expr, alias })
+    }
+
+    // non-negative integer of LIMIT and OFFSET
+    fn parse_count(&mut self, clause: &str) -> Result<u64, Error> {
+        match *self.peek() {
+            Token::Integer(count) => {
+                self.advance();
+                Ok(count as u64)
+            }
+            _ => Err(self.expected(&format!("row count after {}", clause))),
+        }
+    }
+
+    // UPDATE table SET column = expr, ... [WHERE expr]
+    fn parse_update(&mut self) -> Result<Update, Error> {
+        self.expect_keyword(Keyword::Update, "UPDATE")?;
+        let table = self.parse_ident("table name")?;
+        self.expect_keyword(Keyword::Set, "SET after table name")?;
+        let mut assignments = vec![];
+        loop {
+            let column = self.parse_ident("column name")?;
+            self.expect(&Token::Eq, "'=' after column name")?;
+            assignments.push((column, self.parse_expr()?));
+            if !self.consume(&Token::Comma) {
+                break;
+            }
+        }
+        let where_clause = self.parse_where()?;
+        Ok(Update {
+            table,
+            assignments,
+            where_clause,
+        })
+    }
+
+    // DELETE FROM table [WHERE expr]
+    fn parse_delete(&mut self) -> Result<Delete, Error> {
+        self.expect_keyword(Keyword::Delete, "DELETE")?;
+        self.expect_keyword(Keyword::From, "FROM after DELETE")?;
+        let table = self.parse_ident("table name")?;
+        let where_clause = self.parse_where()?;
+        Ok(Delete {
+            table,
+            where_clause,
+        })
+    }
+
+    fn parse_where(&mut self) -> Result<Option<Expr>, Error> {
+        if self.consume_keyword(Keyword::Where) {
+            Ok(Some(self.parse_expr()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, Error> {
+        self.parse_expr_bp(precedence::OR)
+    }
+
+    // Precedence climbing: parse an expression whose operators bind at least as tight as min_precedence
+    fn parse_expr_bp(&mut self, min_precedence: u8) -> Result<Expr, Error> {
+        // NOTE: every nested expression goes through here
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("expression is nested too deeply"));
+        }
+        self.depth += 1;
+        let expr = self.parse_operators(min_precedence);
+        self.depth -= 1;
+        expr
+    }
+
+    fn parse_operators(&mut self, min_precedence: u8) -> Result<Expr, Error> {
+        let mut left = self.parse_prefix()?;
+        loop {
+            if min_precedence <= precedence::COMPARISON && self.consume_keyword(Keyword::Is) {
+                let negated = self.consume_keyword(Keyword::Not);
+                self.expect_keyword(Keyword::Null, "NULL after IS")?;
+                left = Expr::IsNull {
+                    expr: Box::new(left),
+                    negated,
+                };
+                continue;
+            }
+            if min_precedence <= precedence::COMPARISON {
+                if let Some(expr) = self.parse_predicate(&left)? {
+                    left = expr;
+                    continue;
+                }
+            }
+            let op = match self.peek_binary_op() {
+                Some(op) if op.precedence() >= min_precedence => op,
+                _ => return Ok(left),
+            };
+            self.advance();
+            // NOTE: binding the right operand one level tighter makes the operators left associative
+            let right = self.parse_expr_bp(op.precedence() + 1)?;
+            left = Expr::Binary {
+                left: Box::new(left),
+                op,
+                right: Box::new(right),
+            };
+        }
+    }
+
+    // [NOT] IN (expr, ...), [NOT] BETWEEN low AND high or [NOT] LIKE pattern [ESCAPE escape]
+    // after the operand left
+    fn parse_predicate(&mut self, left: &Expr) -> Result<Option<Expr>, Error> {
+        let negated = match (self.peek(), self.peek_second()) {
+            (Token::Keyword(Keyword::In | Keyword::Between | Keyword::Like), _) => false,
+            (
+                Token::Keyword(Keyword::Not),
+                Token::Keyword(Keyword::In | Keyword::Between | Keyword::Like),
+            ) => {
+                self.advance();
+                true
+            }
+            _ => return Ok(None),
+        };
+        let expr = if self.consume_keyword(Keyword::Like) {
+            let pattern = self.parse_expr_bp(precedence::COMPARISON + 1)?;
+            let escape = if self.consume_keyword(Keyword::Escape) {
+                Some(Box::new(self.parse_expr_bp(precedence::COMPARISON + 1)?))
+            } else {
+                None
+            };
+            Expr::Like {
+                expr: Box::new(left.clone()),
+                pattern: Box::new(pattern),
+                escape,
+                negated,
+            }
+        } else if self.consume_keyword(Keyword::In) {
+            self.expect(&Token::LParen, "'(' after IN")?;
+            let mut list = vec![self.parse_expr()?];
+            while self.consume(&Token::Comma) {
+                list.push(self.parse_expr()?);
+            }
+            self.expect(&Token::RParen, "')' after IN list")?;
+            Expr::InList {
+                expr: Box::new(left.clone()),
+                list,
+                negated,
+            }
+        } else {
+            self.advance();
+            // NOTE: the bounds bind tighter than AND, so the AND here separates them
+            let low = self.parse_expr_bp(precedence::COMPARISON + 1)?;
+            self.expect_keyword(Keyword::And, "AND between the BETWEEN bounds")?;
+            let high = self.parse_expr_bp(precedence::COMPARISON + 1)?;
+            Expr::Between {
+                expr: Box::new(left.clone()),
+                low: Box::new(low),
+                high: Box::new(high),
+                negated,
+            }
+        };
+        Ok(Some(expr))
+    }
+
+    fn peek_binary_op(&self) -> Option<BinaryOp> {
+        let op = match self.peek() {
+            Token::Keyword(Keyword::Or) => BinaryOp::Or,
+            Token::Keyword(Keyword::And) => BinaryOp::And,
+            Token::Eq => BinaryOp::Eq,
+            Token::NotEq => BinaryOp::NotEq,
+            Token::Lt => BinaryOp::Lt,
+            Token::LtEq => BinaryOp::LtEq,
+            Token::Gt => BinaryOp::Gt,
+            Token::GtEq => BinaryOp::GtEq,
+            Token::Concat => BinaryOp::Concat,
+            Token::Plus => BinaryOp::Add,
+            Token::Minus => BinaryOp::Sub,
+            Token::Star => BinaryOp::Mul,
+            Token::Slash => BinaryOp::Div,
+            Token::Percent => BinaryOp::Mod,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    fn parse_prefix(&mut self) -> Result<Expr, Error> {
+        let expr = match self.peek().clone() {
+            Token::Keyword(Keyword::Not) => {
+                self.advance();
+                let expr = self.parse_expr_bp(precedence::NOT)?;
+                Expr::Unary {
+                    op: UnaryOp::Not,
+                    expr: Box::new(expr),
+                }
+            }
+            Token::Minus => {
+                self.advance();
+                let expr = self.parse_expr_bp(precedence::UNARY_MINUS)?;
+                Expr::Unary {
+                    op: UnaryOp::Minus,
+                    expr: Box::new(expr),
+                }
+            }
+            Token::LParen => {
+                self.advance();
+                let expr = self.parse_expr()?;
+                self.expect(&Token::RParen, "')' after expression")?;
+                expr
+            }
+            Token::Keyword(Keyword::Null) => {
+                self.advance();
+                Expr::Literal(Literal::Null)
+            }
+            Token::Keyword(Keyword::True) => {
+                self.advance();
+                Expr::Literal(Literal::Boolean(true))
+            }
+            Token::Keyword(Keyword::False) => {
+                self.advance();
+                Expr::Literal(Literal::Boolean(false))
+            }
+            Token::Integer(integer) => {
+                self.advance();
+                Expr::Literal(Literal::Integer(integer))
+            }
+            Token::Float(float) => {
+                self.advance();
+                Expr::Literal(Literal::Float(float))
+            }
+            Token::String(string) => {
+                self.advance();
+                Expr::Literal(Literal::String(string))
+            }
+            Token::Ident(name) => {
+                self.advance();
+                if self.consume(&Token::LParen) {
+                    let mut args = vec![];
+                    if !self.consume(&Token::RParen) {
+                        args.push(self.parse_expr()?);
+                        while self.consume(&Token::Comma) {
+                            args.push(self.parse_expr()?);
+                        }
+                        self.expect(&Token::RParen, "')' after function arguments")?;
+                    }
+                    Expr::Function { name, args }
+                } else {
+                    Expr::Column(name)
+                }
+            }
+            Token::Keyword(Keyword::Cast) => {
+                self.advance();
+                self.expect(&Token::LParen, "'(' after CAST")?;
+                let expr = self.parse_expr()?;
+                self.expect_keyword(Keyword::As, "AS in CAST")?;
+                let data_type = self.parse_data_type()?;
+                self.expect(&Token::RParen, "')' after CAST type")?;
+                Expr::Cast {
+                    expr: Box::new(expr),
+                    data_type,
+                }
+            }
+            _ => return Err(self.expected("an expression")),
+        };
+        Ok(expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        // (input, canonical form)
+        let corpus = [
+            (
+                "create table users (id int primary key, name varchar(32) not null, score float default 0.0, active bool)",
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, score FLOAT DEFAULT 0.0, active BOOLEAN)",
+            ),
+            (
+                "CREATE TABLE t (a INTEGER, b TEXT DEFAULT 'x' NOT NULL, c INTEGER DEFAULT -1, PRIMARY KEY (a, b))",
+                "CREATE TABLE t (a INTEGER, b TEXT NOT NULL DEFAULT 'x', c INTEGER DEFAULT -1, PRIMARY KEY (a, b))",
+            ),
+            ("DROP TABLE t;", "DROP TABLE t"),
+            ("CREATE INDEX idx ON t (a)", "CREATE INDEX idx ON t (a)"),
+            ("create unique index idx on t (a, b)", "CREATE UNIQUE INDEX idx ON t (a, b)"),
+            (
+                "INSERT INTO t VALUES (1, 'it''s', NULL), (2, 'b', TRUE)",
+                "INSERT INTO t VALUES (1, 'it''s', NULL), (2, 'b', TRUE)",
+            ),
+            (
+                "INSERT INTO t (a, \"select\") VALUES (-1.5, 1e3)",
+                "INSERT INTO t (a, \"select\") VALUES (-1.5, 1000.0)",
+            ),
+            ("SELECT * FROM t", "SELECT * FROM t"),
+            ("SELECT 1 + 2 * 3", "SELECT 1 + 2 * 3"),
+            ("SELECT (1 + 2) * 3", "SELECT (1 + 2) * 3"),
+            ("SELECT 1 - (2 - 3), (1 - 2) - 3", "SELECT 1 - (2 - 3), 1 - 2 - 3"),
+            ("SELECT - - a, -(a * b), -a * b", "SELECT -(-a), -(a * b), -a * b"),
+            (
+                "select a as x, b || 'c' from t where not a = 1 and (b < 2 or c is not null) order by a desc, b limit 10 offset 5",
+                "SELECT a AS x, b || 'c' FROM t WHERE NOT a = 1 AND (b < 2 OR c IS NOT NULL) ORDER BY a DESC, b ASC LIMIT 10 OFFSET 5",
+            ),
+            ("SELECT a FROM t WHERE (a = b) IS NULL", "SELECT a FROM t WHERE (a = b) IS NULL"),
+            ("SELECT a FROM t WHERE a = (NOT b)", "SELECT a FROM t WHERE a = (NOT b)"),
+            (
+                "UPDATE t SET a = a + 1, b = 'x' WHERE a >= 10 AND b <> 'y'",
+                "UPDATE t SET a = a + 1, b = 'x' WHERE a >= 10 AND b <> 'y'",
+            ),
+            ("DELETE FROM t", "DELETE FROM t"),
+            (
+                "select a from t where a in (1, 2 + 3) and b not in ('x') or c between 1 and 10 and d not between -1 and b + 1",
+                "SELECT a FROM t WHERE a IN (1, 2 + 3) AND b NOT IN ('x') OR c BETWEEN 1 AND 10 AND d NOT BETWEEN -1 AND b + 1",
+            ),
+            (
+                "SELECT a FROM t WHERE NOT a IN (1) AND (a = 1) IN (TRUE) AND a BETWEEN (1 AND 2) AND 3",
+                "SELECT a FROM t WHERE NOT a IN (1) AND (a = 1) IN (TRUE) AND a BETWEEN (1 AND 2) AND 3",
+            ),
+            (
+                "select upper(name), substr(name, 2, 3) || now(), cast(a + 1 as varchar(10)) from t where coalesce(a, -1) > 0",
+                "SELECT upper(name), substr(name, 2, 3) || now(), CAST(a + 1 AS TEXT) FROM t WHERE coalesce(a, -1) > 0",
+            ),
+            (
+                "select a from t where a like 'x%' and b not like '10!%' escape '!' or c || 'x' like d",
+                "SELECT a FROM t WHERE a LIKE 'x%' AND b NOT LIKE '10!%' ESCAPE '!' OR c || 'x' LIKE d",
+            ),
+            ("delete from t where a % 2 = 0 or a / 2 > 3", "DELETE FROM t WHERE a % 2 = 0 OR a / 2 > 3"),
+        ];
+        for (sql, canonical) in corpus {
+            let statement = parse_statement(sql).unwrap();
+            assert_eq!(canonical, statement.to_string());
+            assert_eq!(statement, parse_statement(&statement.to_string()).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_parse_multiple_statements() {
+        let statements = parse(
+            "CREATE TABLE t (a INTEGER);\n-- comment\nINSERT INTO t VALUES (1);;SELECT a FROM t",
+        )
+        .unwrap();
+        assert_eq!(3, statements.len());
+        assert!(matches!(statements[2], Statement::Select(_)));
+        assert!(parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_error() {
+        // (input, offset, message)
+        let corpus = [
+            (
+                "SELEC 1",
+                0,
+                "expected a statement but found identifier \"SELEC\"",
+            ),
+            (
+                "CREATE TABLE t (\n  a INTEGER,\n  b TEXT\n  c INTEGER)",
+                41,
+                "expected ')' after column list but found identifier \"c\"",
+            ),
+            ("CREATE TABLE t (a BLOB)", 18, "unknown data type BLOB"),
+            (
+                "CREATE TABLE t (a INTEGER, PRIMARY KEY (a), PRIMARY KEY (a))",
+                44,
+                "multiple primary keys for table",
+            ),
+            (
+                "INSERT INTO t VALUES (1, 2",
+                26,
+                "expected ')' after row values but found end of input",
+            ),
+            (
+                "SELECT a FROM t WHERE",
+                21,
+                "expected an expression but found end of input",
+            ),
+            (
+                "SELECT a FROM t LIMIT x",
+                22,
+                "expected row count after LIMIT but found identifier \"x\"",
+            ),
+            (
+                "SELECT a b FROM t",
+                9,
+                "expected end of statement but found identifier \"b\"",
+            ),
+            (
+                "UPDATE t SET a 1",
+                15,
+                "expected '=' after column name but found number 1",
+            ),
+            (
+                "DELETE t",
+                7,
+                "expected FROM after DELETE but found identifier \"t\"",
+            ),
+            (
+                "SELECT a FROM t WHERE a IS 1",
+                27,
+                "expected NULL after IS but found number 1",
+            ),
+            ("SELECT 'abc", 7, "unterminated string"),
+            (
+                "SELECT f(a, ) FROM t",
+                12,
+                "expected an expression but found ')'",
+            ),
+            (
+                "SELECT f(a b) FROM t",
+                11,
+                "expected ')' after function arguments but found identifier \"b\"",
+            ),
+            (
+                "SELECT CAST(a, INTEGER) FROM t",
+                13,
+                "expected AS in CAST but found ','",
+            ),
+            (
+                "SELECT CAST(a AS BLOB) FROM t",
+                17,
+                "unknown data type BLOB",
+            ),
+            (
+                "SELECT a FROM t WHERE a IN ()",
+                28,
+                "expected an expression but found ')'",
+            ),
+            (
+                "SELECT a FROM t WHERE a NOT 1",
+                24,
+                "expected end of statement but found NOT",
+            ),
+            (
+                "SELECT a FROM t WHERE a BETWEEN 1 OR 2",
+                34,
+                "expected AND between the BETWEEN bounds but found OR",
+            ),
+        ];
+        for (sql, offset, message) in corpus {
+            let error = parse_statement(sql).unwrap_err();
+            assert_eq!(
+                (offset, message),
+                (error.offset, error.message.as_str()),
+                "{}",
+                sql
+            );
+        }
+        let error =
+            parse_statement("CREATE TABLE t (\n  a INTEGER,\n  b TEXT\n  c INTEGER)").unwrap_err();
+        assert_eq!(
+            "expected ')' after column list but found identifier \"c\" at line 4, column 3",
+            error.to_string()
+        );
+        let error = parse("SELECT 1 SELECT 2").unwrap_err();
+        assert_eq!(
+            "expected ';' after statement but found SELECT",
+            error.message
+        );
+    }
+
+    #[test]
+    fn test_parse_depth() {
+        let nested = |depth| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(parse_expr(&nested(MAX_DEPTH - 1)).is_ok());
+        let error = parse_expr(&nested(MAX_DEPTH)).unwrap_err();
+        assert_eq!(MAX_DEPTH, error.offset);
+        assert_eq!("expression is nested too deeply", error.message);
+        // deep enough to overflow the stack without the limit
+        let error = parse_statement(&format!("SELECT {}", nested(3000))).unwrap_err();
+        assert_eq!(7 + MAX_DEPTH, error.offset);
+        let error = parse_expr(&"NOT ".repeat(3000)).unwrap_err();
+        assert_eq!(4 * MAX_DEPTH, error.offset);
+        // long flat expressions are not nested
+        assert!(parse_expr(&vec!["1"; 3000].join(" + ")).is_ok());
+    }
+}